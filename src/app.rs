@@ -1,9 +1,33 @@
 use chrono::Datelike;
-use std::collections::HashMap;
+use log::{debug, info, warn};
+use std::path::PathBuf;
 
 // local
-use crate::model::FinItem;
-use crate::views;
+use crate::common;
+use crate::crypto::Cipher;
+use crate::document::DocumentAction;
+use crate::files::{self, FileInbox, FileKind, PickedFile};
+use crate::formats;
+use crate::history::{self, Change};
+use crate::ledger::Ledger;
+use crate::model::{FinItem, ItemTemplate};
+use crate::persistence::{self, StateError};
+use crate::recovery::{self, Backup};
+use crate::rules::{self, Rule};
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+use crate::store::SqliteStore;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sync::SyncFolder;
+use crate::trash::{self, Trashed};
+use crate::undo::UndoStack;
+use crate::views::{self, history_view::ActivityFilter, passphrase_view::PassphraseInput};
+use crate::workspace::{self, Workspace};
+
+/// Parsed items waiting for confirmation in the import preview
+pub(crate) struct PendingImport {
+    pub(crate) name: String,
+    pub(crate) items: Vec<FinItem>,
+}
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -12,6 +36,40 @@ pub struct TemplateApp {
     // Example stuff:
     pub items: Vec<FinItem>,
     pub categories: Vec<String>,
+    /// Import bank credits as income instead of skipping them
+    pub credits_as_income: bool,
+    /// Applied to imported and new items
+    pub rules: Vec<Rule>,
+    /// Favorites shown above the table
+    pub templates: Vec<ItemTemplate>,
+    /// Activity log of the items
+    pub(crate) history: Vec<Change>,
+    /// Who edits on this device, for the activity log
+    pub(crate) user: String,
+    /// Deleted items of the ledger
+    pub(crate) trash: Vec<Trashed>,
+    /// Ask before deleting an item
+    pub(crate) confirm_delete: bool,
+    /// Days deleted items are kept, 0 keeps them until the trash is emptied
+    pub(crate) trash_days: u32,
+    /// Path of the open ledger document, None if not saved yet
+    pub(crate) document: Option<PathBuf>,
+    pub(crate) recent_files: Vec<PathBuf>,
+    /// Name of the shown ledger
+    pub(crate) workspace: String,
+    /// The other ledgers
+    pub(crate) workspaces: Vec<Workspace>,
+    /// Names the file of this device in sync folders
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) device: String,
+    /// The folder the shown ledger is synced through
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) sync: Option<SyncFolder>,
+    /// The items are saved in the SQLite document and left out of the state
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+    pub(crate) stored: bool,
+    /// Storage keys of the states kept as backup, encrypted with the state
+    pub(crate) state_backups: Vec<String>,
 
     // computed stuff:
     // this how you opt-out of serialization of a member
@@ -25,6 +83,93 @@ pub struct TemplateApp {
     pub owners: Vec<String>,
     #[serde(skip)]
     pub owners_compare: (String, String),
+    #[serde(skip)]
+    pub(crate) inbox: FileInbox,
+    #[serde(skip)]
+    pub(crate) pending_import: Option<PendingImport>,
+    /// Text of the quick-add bar
+    #[serde(skip)]
+    pub(crate) quick_add: String,
+    #[serde(skip)]
+    pub(crate) show_rules: bool,
+    /// The ledger as last opened or saved, to tell unsaved changes
+    #[serde(skip)]
+    pub(crate) saved: Ledger,
+    #[serde(skip)]
+    pub(crate) pending_action: Option<DocumentAction>,
+    #[serde(skip)]
+    title: String,
+    /// Name typed for a new ledger
+    #[serde(skip)]
+    pub(crate) new_workspace: String,
+    /// Why the persisted state was not loaded
+    #[serde(skip)]
+    pub(crate) state_error: Option<StateError>,
+    /// Storage key and persisted state to keep before it is overwritten
+    #[serde(skip)]
+    pub(crate) state_backup: Option<(String, String)>,
+    /// The shown ledger as last written to the journal
+    #[serde(skip)]
+    pub(crate) journaled: Option<(String, Ledger)>,
+    #[serde(skip)]
+    pub(crate) journal_time: f64,
+    /// The journal file, None keeps the journal in the storage
+    #[serde(skip)]
+    pub(crate) journal_path: Option<PathBuf>,
+    /// Entries written to the journal since the state was saved
+    #[serde(skip)]
+    pub(crate) journal_entries: usize,
+    #[serde(skip)]
+    pub(crate) backups: Vec<Backup>,
+    #[serde(skip)]
+    pub(crate) show_backups: bool,
+    #[serde(skip)]
+    pub(crate) pending_restore: Option<Backup>,
+    /// Save the state in the next frame, for changes of other ledgers than
+    /// the shown one
+    #[serde(skip)]
+    pub(crate) save_state: bool,
+    #[serde(skip)]
+    pub(crate) undo: UndoStack,
+    #[serde(skip)]
+    pub(crate) show_trash: bool,
+    /// Item to delete once confirmed
+    #[serde(skip)]
+    pub(crate) pending_delete: Option<uuid::Uuid>,
+    /// The items as last recorded in the activity log
+    #[serde(skip)]
+    pub(crate) recorded: Option<Vec<FinItem>>,
+    #[serde(skip)]
+    pub(crate) record_time: f64,
+    #[serde(skip)]
+    pub(crate) show_activity: bool,
+    #[serde(skip)]
+    pub(crate) activity_filter: ActivityFilter,
+    /// Item whose history is shown
+    #[serde(skip)]
+    pub(crate) history_item: Option<uuid::Uuid>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub(crate) sync_time: f64,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub(crate) show_conflicts: bool,
+    /// Encrypts the state and ledger files, None if there is no passphrase
+    #[serde(skip)]
+    pub(crate) cipher: Option<Cipher>,
+    /// The encrypted state until it is unlocked with the passphrase
+    #[serde(skip)]
+    pub(crate) locked: Option<String>,
+    #[serde(skip)]
+    pub(crate) show_passphrase: bool,
+    #[serde(skip)]
+    pub(crate) passphrase_input: PassphraseInput,
+    #[serde(skip)]
+    pub(crate) passphrase_error: Option<String>,
+    /// The open document if it is a SQLite database
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+    #[serde(skip)]
+    pub(crate) store: Option<SqliteStore>,
 }
 
 impl Default for TemplateApp {
@@ -32,6 +177,22 @@ impl Default for TemplateApp {
         Self {
             items: Vec::new(),
             categories: vec!["a".to_string(), "b".into(), "c".into()],
+            credits_as_income: false,
+            rules: Vec::new(),
+            templates: Vec::new(),
+            history: Vec::new(),
+            user: history::default_user(),
+            trash: Vec::new(),
+            confirm_delete: false,
+            trash_days: trash::DEFAULT_TRASH_DAYS,
+            document: None,
+            recent_files: Vec::new(),
+            workspace: workspace::DEFAULT_NAME.to_owned(),
+            workspaces: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            device: uuid::Uuid::new_v4().simple().to_string()[..8].to_owned(),
+            #[cfg(not(target_arch = "wasm32"))]
+            sync: None,
 
             // calculated
             total: 0.0,
@@ -39,6 +200,46 @@ impl Default for TemplateApp {
             selected_month: chrono::offset::Local::now().date_naive().month(),
             owners: Vec::new(),
             owners_compare: ("None".to_owned(), "None".to_owned()),
+            inbox: FileInbox::default(),
+            pending_import: None,
+            quick_add: String::new(),
+            show_rules: false,
+            saved: Ledger::default(),
+            pending_action: None,
+            title: String::new(),
+            new_workspace: String::new(),
+            state_error: None,
+            state_backup: None,
+            journaled: None,
+            journal_time: 0.0,
+            journal_path: None,
+            journal_entries: 0,
+            backups: Vec::new(),
+            show_backups: false,
+            pending_restore: None,
+            save_state: false,
+            undo: UndoStack::default(),
+            show_trash: false,
+            pending_delete: None,
+            recorded: None,
+            record_time: 0.0,
+            show_activity: false,
+            activity_filter: ActivityFilter::default(),
+            history_item: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            sync_time: 0.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            show_conflicts: false,
+            cipher: None,
+            locked: None,
+            show_passphrase: false,
+            passphrase_input: PassphraseInput::default(),
+            passphrase_error: None,
+            #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+            stored: false,
+            state_backups: Vec::new(),
+            #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+            store: None,
         }
     }
 }
@@ -49,20 +250,185 @@ impl TemplateApp {
         // This is also where you can customized the look at feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
-        // Load previous app state (if any).
+        // Load previous app state (if any), migrated to the current version.
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+        let mut app = cc.storage.map(persistence::load).unwrap_or_default();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            app.journal_path = recovery::journal_path(crate::APP_NAME);
         }
 
-        Default::default()
+        // an encrypted state is resumed when unlocked
+        if app.locked.is_none() {
+            app.resume(cc.storage);
+        }
+        app
+    }
+
+    /// Reopen the documents and recover what was not saved before the app
+    /// last exited
+    pub(crate) fn resume(&mut self, storage: Option<&dyn eframe::Storage>) {
+        self.reopen_document();
+        if let Some(storage) = storage {
+            self.backups = recovery::backups(storage);
+            self.replay_journal(storage);
+        }
+        self.purge_old_trash();
+        self.journaled = Some((self.workspace.clone(), self.ledger()));
+    }
+
+    /// Add imported items, skipping bank transactions that were imported before.
+    /// Returns the number of added items.
+    pub(crate) fn import_items(&mut self, imported: Vec<FinItem>) -> usize {
+        let count = self.items.len();
+        for item in imported {
+            // e.g. a json export imported again
+            if self.items.iter().any(|i| i.id == item.id) {
+                debug!("Skipping already present item {}", item.id);
+                continue;
+            }
+            if let Some(fitid) = &item.fitid {
+                if self.items.iter().any(|i| i.fitid.as_ref() == Some(fitid)) {
+                    debug!("Skipping already imported transaction {}", fitid);
+                    continue;
+                }
+            }
+            self.items.push(item);
+        }
+        self.items.len() - count
+    }
+
+    /// Parse a picked file with its importer and show the items in the preview
+    pub(crate) fn import_file(&mut self, file: PickedFile) {
+        let FileKind::Import(format) = file.kind else {
+            return;
+        };
+        match format.parse(&file.bytes, self.credits_as_income) {
+            Ok(items) => self.preview_import(file.name, items),
+            Err(e) => warn!("Failed to import {} as {}: {}", file.name, format.name(), e),
+        }
+    }
+
+    /// Show parsed items in the import preview, next to any already pending
+    fn preview_import(&mut self, name: String, mut items: Vec<FinItem>) {
+        rules::apply_rules(&self.rules, &mut items);
+        match &mut self.pending_import {
+            Some(pending) => {
+                pending.name = format!("{}, {}", pending.name, name);
+                pending.items.extend(items);
+            }
+            None => self.pending_import = Some(PendingImport { name, items }),
+        }
+    }
+
+    /// Add a new item with the rules applied
+    pub(crate) fn add_item(&mut self, mut item: FinItem) {
+        rules::apply_rules(&self.rules, [&mut item]);
+        self.items.push(item);
+    }
+
+    /// Insert a favorite into the selected month, today if that is the current
+    /// month. The template values are kept as they are, without rules.
+    pub(crate) fn insert_template(&mut self, template: &ItemTemplate) {
+        let today = chrono::offset::Local::now().date_naive();
+        let date = if today.year() == self.selected_year && today.month() == self.selected_month {
+            today
+        } else {
+            chrono::NaiveDate::from_ymd_opt(self.selected_year, self.selected_month, 1)
+                .unwrap_or(today)
+        };
+        self.items.push(template.to_item(date));
+    }
+
+    /// Save the item as favorite, unless there is the same one already
+    pub(crate) fn add_template(&mut self, item: &FinItem) {
+        let template = ItemTemplate::from_item(item);
+        if !self.templates.contains(&template) {
+            self.templates.push(template);
+        }
+    }
+
+    /// Apply the rules again to the selected items
+    pub(crate) fn apply_rules_to_selected(&mut self) {
+        let selected = self.items.iter_mut().filter(|i| i.selected);
+        let count = rules::apply_rules(&self.rules, selected);
+        info!("Rules matched {} selected items", count);
+    }
+
+    /// Parse rows pasted from a spreadsheet
+    pub(crate) fn paste_rows(&mut self, text: &str) {
+        match formats::tsv::parse(text) {
+            Ok(items) if !items.is_empty() => self.preview_import("Clipboard".to_owned(), items),
+            Ok(_) => warn!("No rows found in the pasted text"),
+            Err(e) => warn!("Failed to paste rows: {}", e),
+        }
+    }
+
+    /// The selected rows as tab separated text
+    pub(crate) fn copy_selected(&self) -> Option<String> {
+        let selected: Vec<FinItem> = self.items.iter().filter(|i| i.selected).cloned().collect();
+        (!selected.is_empty()).then(|| formats::tsv::write(&selected))
+    }
+
+    /// Add the items of the import preview
+    pub(crate) fn accept_import(&mut self) {
+        if let Some(pending) = self.pending_import.take() {
+            let count = self.import_items(pending.items);
+            info!("Imported {} items from {}", count, pending.name);
+        }
     }
 }
 
 impl eframe::App for TemplateApp {
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // nothing to show until the saved data is decrypted
+        if self.locked.is_some() {
+            views::unlock_view::show(ctx, frame, self);
+            return;
+        }
+
+        // files picked in a previous frame or dropped onto the window
+        while let Some(file) = self.inbox.try_recv(ctx) {
+            self.open_file(file);
+        }
+        let dropped = ctx.input().raw.dropped_files.clone();
+        for file in dropped.iter().filter_map(files::read_dropped) {
+            self.open_dropped(file);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_sync(ctx);
+        self.undo_shortcuts(ctx);
+
+        // document name with a mark for unsaved changes
+        let title = format!(
+            "{}: {}{} - egui-fincal",
+            self.workspace,
+            self.document_name(),
+            if self.is_dirty() { "*" } else { "" }
+        );
+        if title != self.title {
+            files::set_title(frame, &title);
+            self.title = title;
+        }
+
+        // clipboard, unless a text field has focus and handles it
+        if ctx.memory().focus().is_none() {
+            let events = ctx.input().events.clone();
+            for event in events {
+                match event {
+                    egui::Event::Paste(text) => self.paste_rows(&text),
+                    egui::Event::Copy => {
+                        if let Some(text) = self.copy_selected() {
+                            ctx.output().copied_text = text;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
         let Self {
             items,
 
@@ -95,15 +461,16 @@ impl eframe::App for TemplateApp {
         }
         possible_years.sort();
 
-        // select correct year
+        // select correct year, an empty ledger keeps the current one
         if !possible_years.contains(selected_year) {
-            *selected_year = *possible_years.last().unwrap();
+            if let Some(year) = possible_years.last() {
+                *selected_year = *year;
+            }
         }
 
         // to calculate: for each item the calculated value
         owners.clear();
         *total = 0.0;
-        let mut paid_dict: HashMap<String, (f32, f32)> = HashMap::new();
         for item in items_in_month.iter() {
             // the monthly total
             *total += item.price;
@@ -112,20 +479,9 @@ impl eframe::App for TemplateApp {
             if !owners.contains(key) {
                 owners.push(key.to_string());
             }
-
-            // the monthly total for each name
-            let partial_price = item.price * item.ratio;
-            if paid_dict.contains_key(key.as_str()) {
-                paid_dict.entry(key.to_string()).and_modify(|(v1, v2)| {
-                    *v1 += item.price;
-                    *v2 += partial_price;
-                });
-            } else {
-                paid_dict
-                    .entry(key.to_string())
-                    .or_insert((item.price, partial_price));
-            }
         }
+        // the monthly total for each name
+        let paid_dict = common::paid_by_owner(&items_in_month);
 
         ////////////////////////////////
         // Layouts
@@ -136,7 +492,7 @@ impl eframe::App for TemplateApp {
         egui::TopBottomPanel::top("top_panel")
             .min_height(32.0)
             .show(ctx, |ui| {
-                views::top_panel_view::show(ui, _frame, &mut *self);
+                views::top_panel_view::show(ui, frame, &mut *self);
             });
 
         ////////////////////////////////
@@ -167,10 +523,83 @@ impl eframe::App for TemplateApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             views::central_panel_view::show(ui, &mut *self, &items_in_month, &possible_years);
         });
+
+        ////////////////////////////////
+        // windows
+        views::import_preview_view::show(ctx, &mut *self);
+        views::rules_view::show(ctx, &mut *self);
+        views::unsaved_changes_view::show(ctx, &mut *self);
+        views::state_error_view::show(ctx, &mut *self);
+        views::backups_view::show(ctx, &mut *self);
+        views::passphrase_view::show(ctx, frame, &mut *self);
+        views::history_view::show(ctx, &mut *self);
+        views::trash_view::show(ctx, &mut *self);
+        #[cfg(not(target_arch = "wasm32"))]
+        views::conflicts_view::show(ctx, &mut *self);
+        show_drop_hint(ctx);
+
+        if let Some(backup) = self.pending_restore.take() {
+            if let Some(storage) = frame.storage_mut() {
+                self.restore(storage, &backup);
+            }
+        }
+        if std::mem::take(&mut self.save_state) {
+            if let Some(storage) = frame.storage_mut() {
+                eframe::App::save(self, storage);
+                storage.flush();
+            }
+        }
+        self.track_undo(ctx);
+        self.record_changes(ctx);
+        self.write_journal(ctx, frame);
     }
 
     /// Called by the framework to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        // the encrypted state is kept until unlocked
+        if self.locked.is_some() {
+            return;
+        }
+
+        // a saved SQLite document holds the items, the state only holds
+        // the unsaved ones
+        #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+        let items = {
+            self.stored = self.store.is_some() && !self.is_dirty();
+            self.stored.then(|| std::mem::take(&mut self.items))
+        };
+
+        persistence::save(storage, self);
+
+        #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+        if let Some(items) = items {
+            self.items = items;
+        }
+
+        // everything journaled is saved now
+        self.clear_journal(storage);
+        self.journaled = Some((self.workspace.clone(), self.ledger()));
+        self.rotate_backups(storage);
+    }
+}
+
+/// Darken the window while files are dragged over it
+fn show_drop_hint(ctx: &egui::Context) {
+    if ctx.input().raw.hovered_files.is_empty() {
+        return;
     }
+
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("drop_hint"),
+    ));
+    let screen_rect = ctx.input().screen_rect();
+    painter.rect_filled(screen_rect, 0.0, egui::Color32::from_black_alpha(192));
+    painter.text(
+        screen_rect.center(),
+        egui::Align2::CENTER_CENTER,
+        "Drop files to import",
+        egui::TextStyle::Heading.resolve(&ctx.style()),
+        egui::Color32::WHITE,
+    );
 }
@@ -5,7 +5,11 @@
 // };
 
 use chrono::Month;
+use itertools::Itertools;
 use num_traits::FromPrimitive;
+use std::collections::HashMap;
+
+use crate::model::FinItem;
 
 /// Get English name of month for index
 pub(crate) fn to_name(month_idx: u32) -> String {
@@ -16,6 +20,46 @@ pub(crate) fn to_name(month_idx: u32) -> String {
     }
 }
 
+/// Paid and owed totals (price * ratio) per owner
+pub(crate) fn paid_by_owner(items: &[FinItem]) -> HashMap<String, (f32, f32)> {
+    let mut paid_dict: HashMap<String, (f32, f32)> = HashMap::new();
+    for item in items {
+        let (paid, owed) = paid_dict.entry(item.owner.to_string()).or_default();
+        *paid += item.price;
+        *owed += item.price * item.ratio;
+    }
+    paid_dict
+}
+
+/// Spent totals per category
+pub(crate) fn spent_by_category(items: &[FinItem]) -> HashMap<String, f32> {
+    items
+        .iter()
+        .map(|i| {
+            let x = i
+                .category
+                .as_ref()
+                .unwrap_or(&"None".to_string())
+                .to_string();
+            (x, i.price)
+        })
+        .into_group_map()
+        .iter()
+        .map(|(k, v)| {
+            let result: f32 = v.iter().sum();
+            (k.to_string(), result)
+        })
+        .collect::<HashMap<_, _>>()
+}
+
+/// Escape text for xml, html and svg
+pub(crate) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 ////////////////////////////////
 ////////////// IO //////////////
 ////////////////////////////////
@@ -0,0 +1,310 @@
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, OsRng};
+use chacha20poly1305::{AeadCore, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use log::{info, warn};
+use std::cell::RefCell;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::app::TemplateApp;
+use crate::ledger::LedgerError;
+use crate::persistence::{self, StateError};
+
+/// Starts encrypted data, followed by the salt, the nonce and the ciphertext
+const MAGIC: &[u8] = b"FINCALENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Error returned when data can't be encrypted or decrypted
+#[derive(Debug, PartialEq, Eq)]
+pub struct CryptoError(pub(crate) String);
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<CryptoError> for LedgerError {
+    fn from(e: CryptoError) -> Self {
+        LedgerError(e.to_string())
+    }
+}
+
+impl From<CryptoError> for StateError {
+    fn from(e: CryptoError) -> Self {
+        StateError(e.to_string())
+    }
+}
+
+/// Whether the data was encrypted with a passphrase
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Authenticated encryption with a key derived from a passphrase. Data
+/// encrypted with another salt is decrypted with a key derived for it, the
+/// keys are kept per salt. The passphrase is wiped when the cipher is dropped.
+pub(crate) struct Cipher {
+    passphrase: Zeroizing<String>,
+    salt: [u8; SALT_LEN],
+    keys: RefCell<HashMap<[u8; SALT_LEN], ChaCha20Poly1305>>,
+}
+
+impl Cipher {
+    /// Derive a key with a new salt
+    pub(crate) fn new(passphrase: &str) -> Result<Self, CryptoError> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_salt(passphrase, salt)
+    }
+
+    fn with_salt(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, CryptoError> {
+        let key = derive(passphrase, &salt)?;
+        Ok(Cipher {
+            passphrase: Zeroizing::new(passphrase.to_owned()),
+            salt,
+            keys: RefCell::new(HashMap::from([(salt, key)])),
+        })
+    }
+
+    /// Decrypt data and keep its key for encrypting
+    pub(crate) fn open(passphrase: &str, data: &[u8]) -> Result<(Self, Vec<u8>), CryptoError> {
+        let (salt, _) = split(data)?;
+        let cipher = Self::with_salt(passphrase, salt)?;
+        let plain = cipher.decrypt(data)?;
+        Ok((cipher, plain))
+    }
+
+    /// Whether the passphrase is the one of the cipher
+    pub(crate) fn matches(&self, passphrase: &str) -> bool {
+        *self.passphrase == passphrase
+    }
+
+    pub(crate) fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.keys.borrow()[&self.salt]
+            .encrypt(&nonce, plain)
+            .map_err(|_| CryptoError("Failed to encrypt".to_owned()))?;
+        Ok([MAGIC, &self.salt, &nonce, &ciphertext].concat())
+    }
+
+    pub(crate) fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let (salt, rest) = split(data)?;
+        let mut keys = self.keys.borrow_mut();
+        let key = match keys.entry(salt) {
+            Entry::Occupied(key) => key.into_mut(),
+            Entry::Vacant(entry) => entry.insert(derive(&self.passphrase, &salt)?),
+        };
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        key.decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| CryptoError("Wrong passphrase or damaged data".to_owned()))
+    }
+
+    /// Encrypt text for the storage, which only holds text
+    pub(crate) fn seal(&self, plain: &str) -> Result<String, CryptoError> {
+        Ok(STANDARD.encode(self.encrypt(plain.as_bytes())?))
+    }
+
+    pub(crate) fn unseal(&self, sealed: &str) -> Result<String, CryptoError> {
+        let data = STANDARD
+            .decode(sealed.trim())
+            .map_err(|e| CryptoError(e.to_string()))?;
+        String::from_utf8(self.decrypt(&data)?).map_err(|e| CryptoError(e.to_string()))
+    }
+}
+
+/// The key of the passphrase with the salt
+fn derive(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, CryptoError> {
+    let mut key = Key::default();
+    let derived = Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| CryptoError(e.to_string()))
+        .map(|_| ChaCha20Poly1305::new(&key));
+    key.as_mut_slice().zeroize();
+    derived
+}
+
+/// The salt and the rest after it
+fn split(data: &[u8]) -> Result<([u8; SALT_LEN], &[u8]), CryptoError> {
+    let header = MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if !is_encrypted(data) || data.len() < header {
+        return Err(CryptoError("The data is not encrypted".to_owned()));
+    }
+    let mut salt = [0; SALT_LEN];
+    salt.copy_from_slice(&data[MAGIC.len()..MAGIC.len() + SALT_LEN]);
+    Ok((salt, &data[MAGIC.len() + SALT_LEN..]))
+}
+
+/// Text sealed for the storage
+pub(crate) fn unseal_with(passphrase: &str, sealed: &str) -> Result<(Cipher, String), CryptoError> {
+    let data = STANDARD
+        .decode(sealed.trim())
+        .map_err(|e| CryptoError(e.to_string()))?;
+    let (cipher, plain) = Cipher::open(passphrase, &data)?;
+    let plain = String::from_utf8(plain).map_err(|e| CryptoError(e.to_string()))?;
+    Ok((cipher, plain))
+}
+
+impl TemplateApp {
+    /// Read the persisted state encrypted with the passphrase
+    pub(crate) fn unlock(&mut self, storage: &dyn eframe::Storage, passphrase: &str) {
+        let Some(locked) = &self.locked else {
+            return;
+        };
+        match persistence::unlock(locked, passphrase) {
+            Ok(unlocked) => {
+                let journal_path = self.journal_path.take();
+                *self = unlocked;
+                self.journal_path = journal_path;
+                self.resume(Some(storage));
+                info!("Unlocked the saved data");
+            }
+            Err(e) => {
+                warn!("Failed to unlock: {}", e);
+                self.passphrase_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Encrypt the state, the journal and the backups with a new passphrase,
+    /// or decrypt them with None. Ledger files are encrypted when saved next.
+    pub(crate) fn set_passphrase(
+        &mut self,
+        storage: &mut dyn eframe::Storage,
+        passphrase: Option<&str>,
+    ) {
+        let cipher = match passphrase.map(Cipher::new).transpose() {
+            Ok(cipher) => cipher,
+            Err(e) => {
+                self.passphrase_error = Some(e.to_string());
+                return;
+            }
+        };
+
+        // the backups were written with the old passphrase
+        let backups: Vec<(String, TemplateApp)> = self
+            .backups
+            .iter()
+            .filter_map(|b| {
+                let raw = storage.get_string(&b.key())?;
+                match persistence::from_str(&raw, self.cipher.as_ref()) {
+                    Ok(backup) => Some((b.key(), backup)),
+                    Err(e) => {
+                        warn!("Failed to read the backup {}: {}", b.key(), e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        persistence::reseal_backups(
+            storage,
+            &self.state_backups,
+            self.cipher.as_ref(),
+            cipher.as_ref(),
+        );
+        self.cipher = cipher;
+        for (key, backup) in backups {
+            if let Some(raw) = persistence::to_string(&backup, self.cipher.as_ref()) {
+                storage.set_string(&key, raw);
+            }
+        }
+        eframe::App::save(self, storage);
+        storage.flush();
+        self.passphrase_error = None;
+        info!(
+            "{} the saved data",
+            if self.cipher.is_some() {
+                "Encrypted"
+            } else {
+                "Decrypted"
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::Storage;
+
+    use super::*;
+    use crate::persistence::tests::MemoryStorage;
+
+    #[test]
+    fn decrypts_what_it_encrypts() {
+        let cipher = Cipher::new("correct horse").unwrap();
+        let data = cipher.encrypt(b"ledger").unwrap();
+        assert!(is_encrypted(&data));
+        assert_eq!(cipher.decrypt(&data).unwrap(), b"ledger");
+
+        let sealed = cipher.seal("state").unwrap();
+        assert_eq!(cipher.unseal(&sealed).unwrap(), "state");
+        let (opened, plain) = unseal_with("correct horse", &sealed).unwrap();
+        assert_eq!(plain, "state");
+        assert_eq!(opened.salt, cipher.salt);
+    }
+
+    #[test]
+    fn fails_with_the_wrong_passphrase() {
+        let data = Cipher::new("correct horse")
+            .unwrap()
+            .encrypt(b"ledger")
+            .unwrap();
+        let wrong = Cipher::new("battery staple").unwrap();
+        assert!(wrong.decrypt(&data).is_err());
+        assert!(Cipher::open("battery staple", &data).is_err());
+        assert!(!wrong.matches("correct horse"));
+        assert!(wrong.matches("battery staple"));
+    }
+
+    #[test]
+    fn decrypts_data_of_other_salts() {
+        let data = Cipher::new("correct horse")
+            .unwrap()
+            .encrypt(b"ledger")
+            .unwrap();
+        let cipher = Cipher::new("correct horse").unwrap();
+        assert_eq!(cipher.decrypt(&data).unwrap(), b"ledger");
+        assert_eq!(cipher.keys.borrow().len(), 2);
+    }
+
+    #[test]
+    fn fails_on_damaged_data() {
+        let cipher = Cipher::new("correct horse").unwrap();
+        let mut data = cipher.encrypt(b"ledger").unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(cipher.decrypt(&data).is_err());
+        assert!(cipher.decrypt(b"ledger").is_err());
+        assert!(cipher.decrypt(MAGIC).is_err());
+    }
+
+    #[test]
+    fn encrypts_the_state_backups() {
+        let mut storage = MemoryStorage::default();
+        let mut app = TemplateApp::default();
+        app.state_backup = Some(("backup".to_owned(), "(items: [])".to_owned()));
+        persistence::save(&mut storage, &mut app);
+        assert_eq!(app.state_backups, ["backup"]);
+
+        app.set_passphrase(&mut storage, Some("correct horse"));
+        let raw = storage.get_string("backup").unwrap();
+        assert!(!raw.contains("items"));
+        assert!(persistence::from_str(&raw, app.cipher.as_ref()).is_ok());
+
+        // backups kept after the passphrase is set are encrypted right away
+        app.state_backup = Some(("backup".to_owned(), "(items: [])".to_owned()));
+        persistence::save(&mut storage, &mut app);
+        let raw = storage.get_string("backup").unwrap();
+        assert!(!raw.contains("items"));
+        assert_eq!(app.state_backups, ["backup"]);
+
+        app.set_passphrase(&mut storage, None);
+        let raw = storage.get_string("backup").unwrap();
+        assert!(persistence::from_str(&raw, None).is_ok());
+    }
+}
@@ -0,0 +1,353 @@
+use log::{info, warn};
+use std::path::PathBuf;
+
+use crate::app::TemplateApp;
+use crate::crypto;
+use crate::files::{self, FileKind, PickedFile};
+use crate::ledger::{self, Ledger, LedgerError};
+use crate::report::Report;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+use crate::store::{self, ItemFilter, SqliteStore};
+
+/// Document actions that discard unsaved changes, confirmed first
+#[derive(Clone, PartialEq)]
+pub(crate) enum DocumentAction {
+    New,
+    Open,
+    #[cfg(not(target_arch = "wasm32"))]
+    OpenRecent(PathBuf),
+    /// A ledger dropped onto the window
+    OpenFile(PickedFile),
+    /// Close the shown ledger of several
+    CloseLedger,
+}
+
+impl TemplateApp {
+    /// Read the last document as saved. The state keeps the unsaved changes,
+    /// the document tells what they are.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn reopen_document(&mut self) {
+        let Some(path) = self.document.clone() else {
+            return;
+        };
+
+        #[cfg(feature = "sqlite")]
+        if store::is_store(&path) {
+            self.reopen_store(path);
+            return;
+        }
+
+        match std::fs::read(&path).map(|bytes| self.read_ledger(&bytes)) {
+            Ok(Ok(ledger)) => self.saved = ledger,
+            Ok(Err(e)) => warn!("Failed to reopen {}: {}", path.display(), e),
+            Err(e) => warn!("Failed to reopen {}: {}", path.display(), e),
+        }
+    }
+
+    /// The browser has no documents to reopen
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn reopen_document(&mut self) {}
+
+    /// The data saved in ledger documents
+    pub(crate) fn ledger(&self) -> Ledger {
+        Ledger {
+            items: self.items.clone(),
+            categories: self.categories.clone(),
+            rules: self.rules.clone(),
+            templates: self.templates.clone(),
+            history: self.history.clone(),
+            trash: self.trash.clone(),
+        }
+    }
+
+    pub(crate) fn set_ledger(&mut self, ledger: Ledger) {
+        self.items = ledger.items;
+        self.categories = ledger.categories;
+        self.rules = ledger.rules;
+        self.templates = ledger.templates;
+        self.history = ledger.history;
+        self.trash = ledger.trash;
+        // a ledger opened or switched to has no changes to record or undo
+        self.recorded = None;
+        self.clear_undo();
+    }
+
+    /// Read a ledger document, decrypted with the passphrase if it is encrypted
+    pub(crate) fn read_ledger(&self, bytes: &[u8]) -> Result<Ledger, LedgerError> {
+        if !crypto::is_encrypted(bytes) {
+            return Ledger::from_bytes(bytes);
+        }
+        match &self.cipher {
+            Some(cipher) => Ledger::from_bytes(&cipher.decrypt(bytes)?),
+            None => Err(LedgerError(
+                "The ledger is encrypted, set its passphrase first".to_owned(),
+            )),
+        }
+    }
+
+    /// The ledger document, encrypted if there is a passphrase
+    pub(crate) fn ledger_bytes(&self, ledger: &Ledger) -> Result<Vec<u8>, LedgerError> {
+        match &self.cipher {
+            Some(cipher) => Ok(cipher.encrypt(&ledger.to_bytes())?),
+            None => Ok(ledger.to_bytes()),
+        }
+    }
+
+    /// Whether there are changes since the document was opened or saved
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.items != self.saved.items
+            || self.categories != self.saved.categories
+            || self.rules != self.saved.rules
+            || self.templates != self.saved.templates
+            || self.history != self.saved.history
+            || self.trash != self.saved.trash
+    }
+
+    /// File name of the document for the title bar
+    pub(crate) fn document_name(&self) -> String {
+        self.document
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map_or("Untitled".to_owned(), |n| n.to_string_lossy().into_owned())
+    }
+
+    /// Run the action, or ask first if it would discard unsaved changes
+    pub(crate) fn request(&mut self, action: DocumentAction) {
+        if self.is_dirty() {
+            self.pending_action = Some(action);
+        } else {
+            self.run(action);
+        }
+    }
+
+    pub(crate) fn run(&mut self, action: DocumentAction) {
+        match action {
+            DocumentAction::New => {
+                // keep the categories, they are set up once
+                self.set_ledger(Ledger {
+                    categories: self.categories.clone(),
+                    ..Ledger::default()
+                });
+                self.saved = self.ledger();
+                self.document = None;
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    self.sync = None;
+                }
+                #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+                {
+                    self.store = None;
+                }
+            }
+            DocumentAction::Open => self.inbox.pick(FileKind::Ledger),
+            #[cfg(not(target_arch = "wasm32"))]
+            DocumentAction::OpenRecent(path) => {
+                #[cfg(feature = "sqlite")]
+                if store::is_store(&path) {
+                    self.sync = None;
+                    self.open_store(path);
+                    return;
+                }
+                if let Some(file) = files::read_file(FileKind::Ledger, path) {
+                    self.open_file(file);
+                }
+            }
+            DocumentAction::OpenFile(file) => self.open_file(file),
+            DocumentAction::CloseLedger => self.close_workspace(),
+        }
+    }
+
+    /// Open a dropped ledger once unsaved changes are dealt with, imports
+    /// are added to the open ledger right away
+    pub(crate) fn open_dropped(&mut self, file: PickedFile) {
+        match file.kind {
+            FileKind::Ledger => self.request(DocumentAction::OpenFile(file)),
+            _ => self.import_file(file),
+        }
+    }
+
+    /// Open a picked or dropped file as ledger or import it
+    pub(crate) fn open_file(&mut self, file: PickedFile) {
+        let FileKind::Ledger = file.kind else {
+            self.import_file(file);
+            return;
+        };
+        // the sync folder is for the ledger it was set up with
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.sync = None;
+        }
+
+        #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+        if let Some(path) = file.path.clone().filter(|p| store::is_store(p)) {
+            self.open_store(path);
+            return;
+        }
+
+        match self.read_ledger(&file.bytes) {
+            Ok(ledger) => {
+                self.set_ledger(ledger.clone());
+                self.set_document(file.path, ledger);
+                #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+                {
+                    self.store = None;
+                }
+                info!("Opened {}", file.name);
+            }
+            Err(e) => warn!("Failed to open {}: {}", file.name, e),
+        }
+    }
+
+    /// Remember the document as saved
+    fn set_document(&mut self, path: Option<PathBuf>, saved: Ledger) {
+        self.saved = saved;
+        self.document = path;
+        if let Some(path) = &self.document {
+            self.recent_files.retain(|p| p != path);
+            self.recent_files.insert(0, path.clone());
+            self.recent_files.truncate(ledger::MAX_RECENT_FILES);
+        }
+    }
+
+    /// Save to the open document, or ask where to if there is none
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn save(&mut self) {
+        match self.document.clone() {
+            Some(path) => self.write_document(path),
+            None => self.save_as(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn save_as(&mut self) {
+        if let Some(path) = files::pick_save_path("Ledger", ledger::EXTENSION) {
+            self.write_document(path);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_document(&mut self, path: PathBuf) {
+        #[cfg(feature = "sqlite")]
+        if store::is_store(&path) {
+            self.write_store(path);
+            return;
+        }
+
+        let ledger = self.ledger();
+        let written = self
+            .ledger_bytes(&ledger)
+            .and_then(|bytes| std::fs::write(&path, bytes).map_err(|e| LedgerError(e.to_string())));
+        match written {
+            Ok(()) => {
+                self.set_document(Some(path), ledger);
+                #[cfg(feature = "sqlite")]
+                {
+                    self.store = None;
+                }
+            }
+            Err(e) => warn!("Failed to save {}: {}", path.display(), e),
+        }
+    }
+
+    /// Download the ledger, the browser has no documents to save to
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn save(&mut self) {
+        let ledger = self.ledger();
+        match self.ledger_bytes(&ledger) {
+            Ok(bytes) => {
+                files::save_file("Ledger", ledger::EXTENSION, bytes);
+                self.saved = ledger;
+            }
+            Err(e) => warn!("Failed to save the ledger: {}", e),
+        }
+    }
+
+    /// Save as a SQLite database, which also moves the items out of the state
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+    pub(crate) fn save_as_store(&mut self) {
+        if let Some(path) = files::pick_save_path("SQLite ledger", store::EXTENSION) {
+            self.write_store(path);
+        }
+    }
+
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+    fn open_store(&mut self, path: PathBuf) {
+        let opened = SqliteStore::open(&path).and_then(|mut store| Ok((store.load()?, store)));
+        match opened {
+            Ok((ledger, store)) => {
+                info!(
+                    "Opened {} with {} items",
+                    path.display(),
+                    ledger.items.len()
+                );
+                self.set_ledger(ledger.clone());
+                self.set_document(Some(path), ledger);
+                self.store = Some(store);
+            }
+            Err(e) => warn!("Failed to open {}: {}", path.display(), e),
+        }
+    }
+
+    /// Open the database again, and read the items from it if the state was
+    /// saved without them
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+    fn reopen_store(&mut self, path: PathBuf) {
+        let opened = SqliteStore::open(&path).and_then(|mut store| Ok((store.load()?, store)));
+        match opened {
+            Ok((ledger, store)) => {
+                if std::mem::take(&mut self.stored) {
+                    self.set_ledger(ledger.clone());
+                }
+                self.saved = ledger;
+                self.store = Some(store);
+            }
+            Err(e) => warn!("Failed to reopen {}: {}", path.display(), e),
+        }
+    }
+
+    /// Write the changes to the database, opening it if it is a new one.
+    /// Databases are not encrypted, so nothing is written with a passphrase.
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+    fn write_store(&mut self, path: PathBuf) {
+        if self.cipher.is_some() {
+            warn!(
+                "Not saving {}, SQLite documents are not encrypted with the passphrase",
+                path.display()
+            );
+            return;
+        }
+        let store = match self.store.take() {
+            Some(store) if self.document.as_ref() == Some(&path) => Ok(store),
+            // the rows already in there are replaced
+            _ => SqliteStore::open(&path).and_then(|mut store| store.load().map(|_| store)),
+        };
+        let ledger = self.ledger();
+        let written = store.and_then(|mut store| Ok((store.write(&ledger)?, store)));
+        match written {
+            Ok((changed, store)) => {
+                info!("Wrote {} changes to {}", changed, path.display());
+                self.set_document(Some(path), ledger);
+                self.store = Some(store);
+            }
+            Err(e) => warn!("Failed to save {}: {}", path.display(), e),
+        }
+    }
+
+    /// The report of the selected month, queried from the database if the
+    /// document is one without unsaved changes
+    pub(crate) fn month_report(&self) -> Report {
+        #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+        if let Some(store) = self.store.as_ref().filter(|_| !self.is_dirty()) {
+            let from = chrono::NaiveDate::from_ymd_opt(self.selected_year, self.selected_month, 1);
+            let filter = ItemFilter {
+                from,
+                to: from.and_then(|d| d.checked_add_months(chrono::Months::new(1))),
+                ..ItemFilter::default()
+            };
+            if let Ok(items) = store.query(&filter) {
+                return Report::new(&items, self.selected_year, self.selected_month);
+            }
+        }
+        Report::new(&self.items, self.selected_year, self.selected_month)
+    }
+}
@@ -0,0 +1,263 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::formats::ImportFormat;
+use crate::ledger;
+
+/// What a picked file is opened as
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileKind {
+    Import(ImportFormat),
+    Ledger,
+}
+
+impl FileKind {
+    fn name(&self) -> &'static str {
+        match self {
+            FileKind::Import(format) => format.name(),
+            FileKind::Ledger => "Ledger",
+        }
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            FileKind::Import(format) => format.extensions(),
+            #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+            FileKind::Ledger => &[ledger::EXTENSION, crate::store::EXTENSION],
+            #[cfg(not(all(feature = "sqlite", not(target_arch = "wasm32"))))]
+            FileKind::Ledger => &[ledger::EXTENSION],
+        }
+    }
+}
+
+/// A file picked for import or to open as ledger
+#[derive(Clone, PartialEq)]
+pub(crate) struct PickedFile {
+    pub(crate) kind: FileKind,
+    pub(crate) name: String,
+    pub(crate) bytes: Vec<u8>,
+    /// Native only, the browser does not expose paths
+    pub(crate) path: Option<PathBuf>,
+}
+
+/// Collects picked files. The browser file picker is async, so files are
+/// received by the app on a later frame.
+pub(crate) struct FileInbox {
+    sender: Sender<PickedFile>,
+    receiver: Receiver<PickedFile>,
+    /// Repainted when the browser file picker is done, the app may be idle
+    #[cfg(target_arch = "wasm32")]
+    ctx: Option<egui::Context>,
+}
+
+impl Default for FileInbox {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver,
+            #[cfg(target_arch = "wasm32")]
+            ctx: None,
+        }
+    }
+}
+
+impl FileInbox {
+    /// Show the file dialog for the kind of file
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn pick(&self, kind: FileKind) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(kind.name(), kind.extensions())
+            .set_directory("/")
+            .pick_file()
+        else {
+            return;
+        };
+
+        if let Some(file) = read_file(kind, path) {
+            // the receiver lives as long as self
+            _ = self.sender.send(file);
+        }
+    }
+
+    /// Show the browser file picker for the kind of file
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn pick(&self, kind: FileKind) {
+        let sender = self.sender.clone();
+        let ctx = self.ctx.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let Some(handle) = rfd::AsyncFileDialog::new()
+                .add_filter(kind.name(), kind.extensions())
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+
+            let bytes = handle.read().await;
+            _ = sender.send(PickedFile {
+                kind,
+                name: handle.file_name(),
+                bytes,
+                path: None,
+            });
+            if let Some(ctx) = ctx {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// The next picked file, received in a frame of the context
+    pub(crate) fn try_recv(&mut self, ctx: &egui::Context) -> Option<PickedFile> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.ctx = Some(ctx.clone());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = ctx;
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Read a file from disk
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn read_file(kind: FileKind, path: PathBuf) -> Option<PickedFile> {
+    match std::fs::read(&path) {
+        Ok(bytes) => Some(PickedFile {
+            kind,
+            name: path.display().to_string(),
+            bytes,
+            path: Some(path),
+        }),
+        Err(e) => {
+            log::warn!("Failed to read file {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Pick a destination and write the contents to it
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save_file(name: &str, extension: &str, contents: impl AsRef<[u8]>) {
+    if let Some(path) = pick_save_path(name, extension) {
+        if let Err(e) = std::fs::write(path.as_path(), contents) {
+            log::warn!("Failed to write file {}", e);
+        }
+    }
+}
+
+/// Show the save dialog
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn pick_save_path(name: &str, extension: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter(name, &[extension])
+        .set_directory("/")
+        .save_file()
+}
+
+/// Show the folder dialog
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn pick_folder() -> Option<PathBuf> {
+    rfd::FileDialog::new().pick_folder()
+}
+
+/// Milliseconds until the url of a download is revoked
+#[cfg(target_arch = "wasm32")]
+const REVOKE_DELAY_MS: i32 = 60_000;
+
+/// Offer the contents as a browser download
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn save_file(_name: &str, extension: &str, contents: impl AsRef<[u8]>) {
+    use wasm_bindgen::JsCast;
+
+    let download = || -> Result<(), wasm_bindgen::JsValue> {
+        let bytes = js_sys::Uint8Array::from(contents.as_ref());
+        let parts = js_sys::Array::of1(&bytes);
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .ok_or("no document")?;
+        let anchor = document
+            .create_element("a")?
+            .dyn_into::<web_sys::HtmlAnchorElement>()?;
+        anchor.set_href(&url);
+        anchor.set_download(&format!("egui-fincal.{extension}"));
+        anchor.click();
+
+        // the download starts after the click returns, the url must outlive it
+        let revoke = wasm_bindgen::closure::Closure::once_into_js(move || {
+            _ = web_sys::Url::revoke_object_url(&url);
+        });
+        web_sys::window()
+            .ok_or("no window")?
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                revoke.unchecked_ref(),
+                REVOKE_DELAY_MS,
+            )?;
+        Ok(())
+    };
+
+    if let Err(e) = download() {
+        log::warn!("Failed to download file {:?}", e);
+    }
+}
+
+/// Read a file dropped onto the window and detect its format. Native drops
+/// have a path, web drops come with the contents.
+pub(crate) fn read_dropped(file: &egui::DroppedFile) -> Option<PickedFile> {
+    let name = match &file.path {
+        Some(path) if file.name.is_empty() => path.display().to_string(),
+        _ => file.name.clone(),
+    };
+
+    let bytes = if let Some(bytes) = &file.bytes {
+        bytes.to_vec()
+    } else {
+        #[cfg(not(target_arch = "wasm32"))]
+        match file.path.as_ref().map(std::fs::read) {
+            Some(Ok(bytes)) => bytes,
+            Some(Err(e)) => {
+                log::warn!("Failed to read dropped file {}: {}", name, e);
+                return None;
+            }
+            None => return None,
+        }
+        #[cfg(target_arch = "wasm32")]
+        return None;
+    };
+
+    let is_ledger = FileKind::Ledger
+        .extensions()
+        .iter()
+        .any(|e| name.ends_with(&format!(".{e}")));
+    let kind = if is_ledger {
+        FileKind::Ledger
+    } else if let Some(format) = ImportFormat::detect(&name, &bytes) {
+        FileKind::Import(format)
+    } else {
+        log::warn!("No importer for dropped file {}", name);
+        return None;
+    };
+    Some(PickedFile {
+        kind,
+        name,
+        bytes,
+        path: file.path.clone(),
+    })
+}
+
+/// Set the window title
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn set_title(frame: &mut eframe::Frame, title: &str) {
+    frame.set_window_title(title);
+}
+
+/// Set the title of the browser tab
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn set_title(_frame: &mut eframe::Frame, title: &str) {
+    if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+        document.set_title(title);
+    }
+}
@@ -0,0 +1,256 @@
+use chrono::NaiveDate;
+use itertools::Itertools;
+use log::debug;
+use std::fmt;
+
+use crate::model::FinItem;
+
+pub(crate) mod camt;
+pub(crate) mod csv;
+pub(crate) mod ical;
+pub(crate) mod journal;
+pub(crate) mod json;
+pub(crate) mod mt940;
+pub(crate) mod ods;
+pub(crate) mod ofx;
+pub(crate) mod qif;
+pub(crate) mod splitwise;
+pub(crate) mod spreadsheet;
+pub(crate) mod tricount;
+pub(crate) mod tsv;
+pub(crate) mod xlsx;
+
+/// The file formats that can be imported
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ImportFormat {
+    Csv,
+    Json,
+    Ofx,
+    Qif,
+    Camt,
+    Mt940,
+    Splitwise,
+    Tricount,
+}
+
+impl ImportFormat {
+    pub(crate) const ALL: [ImportFormat; 8] = [
+        ImportFormat::Csv,
+        ImportFormat::Json,
+        ImportFormat::Ofx,
+        ImportFormat::Qif,
+        ImportFormat::Camt,
+        ImportFormat::Mt940,
+        ImportFormat::Splitwise,
+        ImportFormat::Tricount,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv => "CSV",
+            ImportFormat::Json => "JSON",
+            ImportFormat::Ofx => "OFX/QFX",
+            ImportFormat::Qif => "QIF",
+            ImportFormat::Camt => "CAMT.053",
+            ImportFormat::Mt940 => "MT940",
+            ImportFormat::Splitwise => "Splitwise",
+            ImportFormat::Tricount => "Tricount",
+        }
+    }
+
+    pub(crate) fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            ImportFormat::Csv | ImportFormat::Splitwise | ImportFormat::Tricount => &["csv"],
+            ImportFormat::Json => &["json"],
+            ImportFormat::Ofx => &["ofx", "qfx"],
+            ImportFormat::Qif => &["qif"],
+            ImportFormat::Camt => &["xml"],
+            ImportFormat::Mt940 => &["sta", "mt940", "txt"],
+        }
+    }
+
+    /// Guess the format of a file from its extension, csv flavours are told
+    /// apart by their header
+    pub(crate) fn detect(name: &str, bytes: &[u8]) -> Option<ImportFormat> {
+        let extension = name.rsplit_once('.')?.1.to_lowercase();
+        if extension == "csv" {
+            let contents = String::from_utf8_lossy(bytes);
+            let header = contents.lines().next().unwrap_or_default();
+            return Some(if header.starts_with("Date,Description,Category,Cost") {
+                ImportFormat::Splitwise
+            } else if header.contains("Paid by") {
+                ImportFormat::Tricount
+            } else {
+                ImportFormat::Csv
+            });
+        }
+        ImportFormat::ALL
+            .into_iter()
+            .find(|f| f.extensions().contains(&extension.as_str()))
+    }
+
+    /// Parse the file contents, invalid utf-8 is replaced
+    pub(crate) fn parse(
+        &self,
+        bytes: &[u8],
+        credits_as_income: bool,
+    ) -> Result<Vec<FinItem>, ImportError> {
+        let contents = String::from_utf8_lossy(bytes);
+        match self {
+            ImportFormat::Csv => Ok(csv::parse(&contents)),
+            ImportFormat::Json => json::parse(&contents),
+            ImportFormat::Ofx => ofx::parse(&contents, credits_as_income),
+            ImportFormat::Qif => qif::parse(&contents, credits_as_income),
+            ImportFormat::Camt => camt::parse(&contents, credits_as_income),
+            ImportFormat::Mt940 => mt940::parse(&contents, credits_as_income),
+            ImportFormat::Splitwise => splitwise::parse(&contents),
+            ImportFormat::Tricount => tricount::parse(&contents),
+        }
+    }
+}
+
+/// Error returned by the file format importers
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImportError(pub(crate) String);
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Commodity of all exported amounts
+pub(crate) const COMMODITY: &str = "EUR";
+
+/// Category of credits imported as income
+pub(crate) const INCOME_CATEGORY: &str = "Income";
+
+/// Turn a signed bank amount into an item. Debits become expenses, credits are
+/// skipped or booked as income with a negative price.
+pub(crate) fn bank_item(
+    date: NaiveDate,
+    name: String,
+    amount: f32,
+    credits_as_income: bool,
+) -> Option<FinItem> {
+    if amount < 0.0 {
+        return Some(FinItem::new(date, name, -amount));
+    }
+    if !credits_as_income {
+        debug!("Skipping credit of {} on {}", amount, date);
+        return None;
+    }
+
+    let mut item = FinItem::new(date, name, -amount);
+    item.category = Some(INCOME_CATEGORY.to_owned());
+    Some(item)
+}
+
+/// Split a delimited line into fields, honouring double quotes
+pub(crate) fn split_fields(line: &str, separator: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Quote a field if it contains the separator or quotes
+pub(crate) fn quote_field(field: &str, separator: char) -> String {
+    if field.contains(separator) || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// What each other person owes the owner of an item, the ratio share is split
+/// evenly between them
+pub(crate) fn owed_by<'a>(item: &FinItem, people: &'a [String]) -> Vec<(&'a str, f32)> {
+    let others: Vec<&str> = people
+        .iter()
+        .map(String::as_str)
+        .filter(|p| *p != item.owner)
+        .collect();
+    let share = item.price * item.ratio / others.len().max(1) as f32;
+    others.into_iter().map(|p| (p, share)).collect()
+}
+
+/// All owners of the items, sorted
+pub(crate) fn people(items: &[FinItem]) -> Vec<String> {
+    items
+        .iter()
+        .map(|i| i.owner.clone())
+        .unique()
+        .sorted()
+        .collect()
+}
+
+/// Parse an amount written with either decimal separator. The last separator
+/// is the decimal one, the others group thousands: `1.234,50` and `1,234.50`
+/// are both 1234.5. A separator that appears more than once, or a lone one
+/// followed by three digits as in `1,234`, groups thousands.
+pub(crate) fn parse_amount(field: &str) -> Result<f32, ImportError> {
+    let field = field.trim();
+    let decimal = field.rfind([',', '.']).filter(|&i| {
+        field.matches(&field[i..=i]).count() == 1 && !groups_thousands(&field[..i], &field[i + 1..])
+    });
+    let number: String = field
+        .char_indices()
+        .filter_map(|(i, c)| match c {
+            _ if Some(i) == decimal => Some('.'),
+            ',' | '.' => None,
+            c => Some(c),
+        })
+        .collect();
+    number
+        .parse::<f32>()
+        .map_err(|_| ImportError(format!("Invalid amount {field}")))
+}
+
+/// Whether a lone separator between the digits groups thousands
+fn groups_thousands(before: &str, after: &str) -> bool {
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let before = before.trim_start_matches(['-', '+']);
+    after.len() == 3
+        && digits(after)
+        && (1..=3).contains(&before.len())
+        && digits(before)
+        && !before.starts_with('0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_amounts_with_either_separator() {
+        assert_eq!(parse_amount("12.50"), Ok(12.5));
+        assert_eq!(parse_amount(" -12,50 "), Ok(-12.5));
+        assert_eq!(parse_amount("1.234,50"), Ok(1234.5));
+        assert_eq!(parse_amount("1,234.50"), Ok(1234.5));
+        assert_eq!(parse_amount("1,234,567"), Ok(1234567.0));
+        assert_eq!(parse_amount("1.234.567,5"), Ok(1234567.5));
+        assert_eq!(parse_amount("42"), Ok(42.0));
+        assert_eq!(parse_amount("1,234"), Ok(1234.0));
+        assert_eq!(parse_amount("1.234"), Ok(1234.0));
+        assert_eq!(parse_amount("-1,500"), Ok(-1500.0));
+        assert_eq!(parse_amount("0.125"), Ok(0.125));
+        assert_eq!(parse_amount("1,2345"), Ok(1.2345));
+        assert_eq!(parse_amount("1234,567"), Ok(1234.567));
+        assert!(parse_amount("").is_err());
+        assert!(parse_amount("twelve").is_err());
+    }
+}
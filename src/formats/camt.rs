@@ -0,0 +1,181 @@
+use chrono::NaiveDate;
+use log::warn;
+use roxmltree::{Document, Node};
+
+use crate::formats::{bank_item, ImportError};
+use crate::model::FinItem;
+
+/// Parse the entries (Ntry) of a CAMT.053 bank statement into items
+///
+/// Debits become items with a positive price, credits are skipped unless
+/// `credits_as_income` is set.
+pub(crate) fn parse(input: &str, credits_as_income: bool) -> Result<Vec<FinItem>, ImportError> {
+    let doc = Document::parse(input).map_err(|e| ImportError(e.to_string()))?;
+    if doc.descendants().all(|n| !is(&n, "BkToCstmrStmt")) {
+        return Err(ImportError("Not a CAMT.053 statement".to_owned()));
+    }
+
+    let mut items = Vec::new();
+    for entry in doc.descendants().filter(|n| is(n, "Ntry")) {
+        if let Some(item) = to_item(&entry, credits_as_income)? {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+fn to_item(entry: &Node<'_, '_>, credits_as_income: bool) -> Result<Option<FinItem>, ImportError> {
+    // Dt or DtTm, only the date is relevant
+    let booked = path(entry, &["BookgDt"])
+        .and_then(|n| n.children().find(|c| is(c, "Dt") || is(c, "DtTm")))
+        .and_then(|n| n.text())
+        .ok_or_else(|| ImportError("Entry without booking date".to_owned()))?;
+    let date = booked
+        .get(..10)
+        .and_then(|d| d.parse::<NaiveDate>().ok())
+        .ok_or_else(|| ImportError(format!("Invalid booking date {booked}")))?;
+    let amount = text(entry, &["Amt"])
+        .and_then(|a| a.parse::<f32>().ok())
+        .ok_or_else(|| ImportError(format!("Invalid amount in entry of {date}")))?;
+    let credit = match text(entry, &["CdtDbtInd"]) {
+        Some("CRDT") => true,
+        Some("DBIT") => false,
+        other => {
+            return Err(ImportError(format!(
+                "Invalid credit/debit indicator {other:?} in entry of {date}"
+            )))
+        }
+    };
+
+    // the counterparty is the creditor for debits and the debtor for credits
+    let details = path(entry, &["NtryDtls", "TxDtls"]);
+    let party = if credit { "Dbtr" } else { "Cdtr" };
+    let counterparty = details
+        .as_ref()
+        .and_then(|d| {
+            text(d, &["RltdPties", party, "Nm"])
+                .or_else(|| text(d, &["RltdPties", party, "Pty", "Nm"]))
+        })
+        .map(str::to_owned);
+    let remittance = details
+        .as_ref()
+        .and_then(|d| path(d, &["RmtInf"]))
+        .map(|r| {
+            r.children()
+                .filter(|c| is(c, "Ustrd"))
+                .filter_map(|c| c.text())
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|r| !r.is_empty())
+        .or_else(|| text(entry, &["AddtlNtryInf"]).map(str::to_owned));
+
+    let (name, memo) = match (counterparty, remittance) {
+        (Some(name), memo) => (name, memo),
+        (None, Some(memo)) => (memo, None),
+        (None, None) => {
+            warn!("Entry of {} without counterparty or remittance info", date);
+            (String::new(), None)
+        }
+    };
+
+    let signed = if credit { amount } else { -amount };
+    let item = bank_item(date, name, signed, credits_as_income).map(|mut item| {
+        item.memo = memo;
+        item.fitid = text(entry, &["AcctSvcrRef"])
+            .or_else(|| text(entry, &["NtryRef"]))
+            .map(str::to_owned);
+        item.account = entry
+            .ancestors()
+            .find(|n| is(n, "Stmt"))
+            .and_then(|s| {
+                text(&s, &["Acct", "Id", "IBAN"])
+                    .or_else(|| text(&s, &["Acct", "Id", "Othr", "Id"]))
+            })
+            .map(str::to_owned);
+        item
+    });
+    Ok(item)
+}
+
+/// Compare the local name, ignoring the camt version namespace
+fn is(node: &Node<'_, '_>, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+}
+
+/// Follow a path of child elements
+fn path<'a, 'input>(node: &Node<'a, 'input>, names: &[&str]) -> Option<Node<'a, 'input>> {
+    names
+        .iter()
+        .try_fold(*node, |n, name| n.children().find(|c| is(c, name)))
+}
+
+/// Trimmed text of the element at the end of the path
+fn text<'a>(node: &Node<'a, '_>, names: &[&str]) -> Option<&'a str> {
+    path(node, names)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE02120300000000202051</IBAN></Id></Acct>
+      <Ntry>
+        <NtryRef>N-1</NtryRef>
+        <Amt Ccy="EUR">12.50</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2023-05-01</Dt></BookgDt>
+        <AcctSvcrRef>REF-1</AcctSvcrRef>
+        <NtryDtls><TxDtls>
+          <RltdPties><Cdtr><Nm>Bakery</Nm></Cdtr></RltdPties>
+          <RmtInf><Ustrd>Card payment</Ustrd><Ustrd>May 1</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <NtryRef>N-2</NtryRef>
+        <Amt Ccy="EUR">2500.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><DtTm>2023-05-02T08:00:00</DtTm></BookgDt>
+        <NtryDtls><TxDtls>
+          <RltdPties><Dbtr><Nm>Employer</Nm></Dbtr></RltdPties>
+        </TxDtls></NtryDtls>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn parses_entries() {
+        let items = parse(STATEMENT, false).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].date, NaiveDate::from_ymd_opt(2023, 5, 1).unwrap());
+        assert_eq!(items[0].item, "Bakery");
+        assert_eq!(items[0].memo.as_deref(), Some("Card payment May 1"));
+        assert_eq!(items[0].price, 12.5);
+        assert_eq!(items[0].fitid.as_deref(), Some("REF-1"));
+        assert_eq!(items[0].account.as_deref(), Some("DE02120300000000202051"));
+
+        let items = parse(STATEMENT, true).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].date, NaiveDate::from_ymd_opt(2023, 5, 2).unwrap());
+        assert_eq!(items[1].item, "Employer");
+        assert_eq!(items[1].price, -2500.0);
+        // without AcctSvcrRef
+        assert_eq!(items[1].fitid.as_deref(), Some("N-2"));
+    }
+
+    #[test]
+    fn fails_on_an_unknown_indicator() {
+        let input = STATEMENT.replace("DBIT", "DEBIT");
+        assert!(parse(&input, false).is_err());
+        assert!(parse("<Document/>", false).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use log::warn;
+
+use crate::model::FinItem;
+
+/// Parse csv lines into items, skipping lines that fail to parse
+pub(crate) fn parse(input: &str) -> Vec<FinItem> {
+    let mut items = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        match line.parse::<FinItem>() {
+            Ok(item) => items.push(item),
+            Err(_) => warn!("Failed to parse line {}", i),
+        }
+    }
+    items
+}
+
+/// Write items as csv, one item per line
+pub(crate) fn write(items: &[FinItem]) -> String {
+    items.iter().map(|i| format!("{i}\n")).collect()
+}
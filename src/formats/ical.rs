@@ -0,0 +1,108 @@
+use crate::formats::COMMODITY;
+use crate::model::{FinItem, Recurrence};
+
+/// Write items as an iCalendar file with one all-day event per item.
+/// Recurring bills repeat with an RRULE.
+pub(crate) fn write(items: &[FinItem]) -> String {
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//egui-fincal//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+    ];
+
+    for item in items {
+        let category = item.category.as_deref().unwrap_or("None");
+        let description = format!(
+            "Amount: {:.2} {COMMODITY}\nCategory: {category}\nPaid by: {}\nRatio: {}",
+            item.price, item.owner, item.ratio
+        );
+
+        lines.push("BEGIN:VEVENT".to_owned());
+        // stable, so re-exported events replace the old ones in calendar apps
+        lines.push(format!("UID:{}@egui-fincal", item.id));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", item.date.format("%Y%m%d")));
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape(&format!("{} ({:.2} {COMMODITY})", item.item, item.price))
+        ));
+        lines.push(format!("DESCRIPTION:{}", escape(&description)));
+        lines.push(format!("CATEGORIES:{}", escape(category)));
+        if let Some(recurrence) = item.recurrence {
+            let freq = match recurrence {
+                Recurrence::Weekly => "WEEKLY",
+                Recurrence::Monthly => "MONTHLY",
+                Recurrence::Yearly => "YEARLY",
+            };
+            lines.push(format!("RRULE:FREQ={freq}"));
+        }
+        lines.push("END:VEVENT".to_owned());
+    }
+    lines.push("END:VCALENDAR".to_owned());
+
+    lines.iter().map(|l| fold(l)).collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold lines longer than 75 octets and terminate them with CRLF
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn writes_an_event_per_item() {
+        let date = NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+        let mut rent = FinItem::new(date, "Rent, May".to_owned(), 800.0);
+        rent.recurrence = Some(Recurrence::Monthly);
+        let calendar = write(&[rent.clone()]);
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.contains(&format!("UID:{}@egui-fincal\r\n", rent.id)));
+        assert!(calendar.contains("DTSTART;VALUE=DATE:20230501\r\n"));
+        assert!(calendar.contains("SUMMARY:Rent\\, May"));
+        assert!(calendar.contains("RRULE:FREQ=MONTHLY\r\n"));
+        assert!(calendar.lines().all(|l| l.len() <= 76));
+    }
+
+    #[test]
+    fn keeps_the_uid_of_edited_items() {
+        let date = NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+        let rent = FinItem::new(date, "Rent".to_owned(), 800.0);
+        let raised = FinItem {
+            price: 900.0,
+            ..rent.clone()
+        };
+        let uid = |calendar: String| {
+            calendar
+                .lines()
+                .find(|l| l.starts_with("UID:"))
+                .map(str::to_owned)
+        };
+        assert_eq!(uid(write(&[rent])), uid(write(&[raised])));
+    }
+}
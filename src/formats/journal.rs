@@ -0,0 +1,197 @@
+use itertools::Itertools;
+
+use crate::formats::{people, COMMODITY};
+use crate::model::FinItem;
+
+/// Write items as an hledger/ledger-cli journal
+pub(crate) fn write_ledger(items: &[FinItem]) -> String {
+    let people = people(items);
+    let mut out = String::new();
+    for item in items {
+        out.push_str(&format!("{} {}", item.date, item.item));
+        if let Some(memo) = &item.memo {
+            out.push_str(&format!("  ; {memo}"));
+        }
+        out.push('\n');
+        for (account, amount) in postings(item, &people) {
+            out.push_str(&format!("    {account:<40} {amount:>10.2} {COMMODITY}\n"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Write items as a beancount file, opening all used accounts first
+pub(crate) fn write_beancount(items: &[FinItem]) -> String {
+    let people = people(items);
+    let mut out = format!("option \"operating_currency\" \"{COMMODITY}\"\n\n");
+
+    if let Some(first) = items.iter().map(|i| i.date).min() {
+        let accounts = items
+            .iter()
+            .flat_map(|i| postings(i, &people))
+            .map(|(account, _)| account)
+            .unique()
+            .sorted();
+        for account in accounts {
+            out.push_str(&format!("{first} open {account}\n"));
+        }
+        out.push('\n');
+    }
+
+    for item in items {
+        let item_str = quote(&item.item);
+        match &item.memo {
+            Some(memo) => out.push_str(&format!("{} * {} {}\n", item.date, item_str, quote(memo))),
+            None => out.push_str(&format!("{} * {}\n", item.date, item_str)),
+        }
+        for (account, amount) in postings(item, &people) {
+            out.push_str(&format!("  {account:<40} {amount:>10.2} {COMMODITY}\n"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The balanced postings of an item.
+///
+/// The owner pays the full price from their account. Their expense is the
+/// price less the ratio share, which the other person (or "Others" if the
+/// ledger isn't shared by two) owes them.
+fn postings(item: &FinItem, people: &[String]) -> Vec<(String, f32)> {
+    let category = account_name(item.category.as_deref().unwrap_or("Uncategorized"));
+    let owner = account_name(&item.owner);
+    let kind = if item.price < 0.0 {
+        "Income"
+    } else {
+        "Expenses"
+    };
+
+    let share = item.price * item.ratio;
+    let mut postings = Vec::new();
+    // nothing left to post if all of it is owed
+    if item.price - share != 0.0 || share == 0.0 {
+        postings.push((format!("{kind}:{category}"), item.price - share));
+    }
+    if share != 0.0 {
+        let other = match people {
+            [a, b] if *a == item.owner => account_name(b),
+            [a, b] if *b == item.owner => account_name(a),
+            _ => "Others".to_owned(),
+        };
+        postings.push((format!("Assets:Receivable:{other}"), share));
+    }
+    postings.push((format!("Assets:{owner}"), -item.price));
+    postings
+}
+
+/// Make a valid account component: capitalized, alphanumeric and dashes
+fn account_name(name: &str) -> String {
+    let name: String = name
+        .split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-')
+        .collect();
+
+    match name.chars().next() {
+        None => "Unknown".to_owned(),
+        Some(c) if !c.is_alphabetic() => format!("X{name}"),
+        Some(_) => name,
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn items() -> Vec<FinItem> {
+        let mut rent = FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            "Rent".to_owned(),
+            800.0,
+        );
+        rent.category = Some("bills".to_owned());
+        rent.owner = "anna".to_owned();
+        rent.ratio = 0.25;
+        let mut coffee = FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 2).unwrap(),
+            "Coffee \"Zum Anker\"".to_owned(),
+            3.5,
+        );
+        coffee.category = None;
+        coffee.owner = "ben".to_owned();
+        coffee.ratio = 0.0;
+        coffee.memo = Some("card".to_owned());
+        vec![rent, coffee]
+    }
+
+    #[test]
+    fn writes_a_journal() {
+        let expected = "\
+2023-05-01 Rent
+    Expenses:Bills                               600.00 EUR
+    Assets:Receivable:Ben                        200.00 EUR
+    Assets:Anna                                 -800.00 EUR
+
+2023-05-02 Coffee \"Zum Anker\"  ; card
+    Expenses:Uncategorized                         3.50 EUR
+    Assets:Ben                                    -3.50 EUR
+
+";
+        assert_eq!(write_ledger(&items()), expected);
+    }
+
+    #[test]
+    fn writes_beancount() {
+        let expected = "\
+option \"operating_currency\" \"EUR\"
+
+2023-05-01 open Assets:Anna
+2023-05-01 open Assets:Ben
+2023-05-01 open Assets:Receivable:Ben
+2023-05-01 open Expenses:Bills
+2023-05-01 open Expenses:Uncategorized
+
+2023-05-01 * \"Rent\"
+  Expenses:Bills                               600.00 EUR
+  Assets:Receivable:Ben                        200.00 EUR
+  Assets:Anna                                 -800.00 EUR
+
+2023-05-02 * \"Coffee \\\"Zum Anker\\\"\" \"card\"
+  Expenses:Uncategorized                         3.50 EUR
+  Assets:Ben                                    -3.50 EUR
+
+";
+        assert_eq!(write_beancount(&items()), expected);
+    }
+
+    #[test]
+    fn balances_the_postings() {
+        let mut owed = items().remove(0);
+        owed.ratio = 1.0;
+        let mut income = items().remove(0);
+        income.price = -1000.0;
+        income.ratio = 0.5;
+        let people = ["anna".to_owned(), "ben".to_owned()];
+        for item in [owed, income] {
+            let postings = postings(&item, &people);
+            assert_eq!(postings.iter().map(|(_, amount)| amount).sum::<f32>(), 0.0);
+        }
+        assert_eq!(postings(&items()[0], &[]).len(), 3);
+        assert_eq!(postings(&items()[0], &[])[1].0, "Assets:Receivable:Others");
+    }
+}
@@ -0,0 +1,12 @@
+use crate::formats::ImportError;
+use crate::model::FinItem;
+
+/// Parse a json array of items
+pub(crate) fn parse(input: &str) -> Result<Vec<FinItem>, ImportError> {
+    serde_json::from_str(input).map_err(|e| ImportError(e.to_string()))
+}
+
+/// Write items as a json array
+pub(crate) fn write(items: &[FinItem]) -> String {
+    serde_json::to_string_pretty(items).unwrap_or_default()
+}
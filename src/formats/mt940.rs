@@ -0,0 +1,222 @@
+use chrono::{Datelike, NaiveDate};
+use log::warn;
+
+use crate::formats::{bank_item, ImportError};
+use crate::model::FinItem;
+
+/// A :61: statement line with the following :86: information
+struct StatementLine {
+    line: String,
+    info: Option<String>,
+    account: Option<String>,
+}
+
+/// Parse the statement lines (:61:) of an MT940 file into items
+///
+/// Debits become items with a positive price, credits are skipped unless
+/// `credits_as_income` is set.
+pub(crate) fn parse(input: &str, credits_as_income: bool) -> Result<Vec<FinItem>, ImportError> {
+    let mut lines: Vec<StatementLine> = Vec::new();
+    // the :25: account identification of the current statement
+    let mut account: Option<String> = None;
+    // the field that continuation lines are appended to
+    let mut field: Option<&str> = None;
+
+    for raw in input.lines() {
+        let raw = raw.trim_end();
+        if let Some(value) = raw.strip_prefix(":61:") {
+            lines.push(StatementLine {
+                line: value.to_owned(),
+                info: None,
+                account: account.clone(),
+            });
+            field = Some("61");
+        } else if let Some(value) = raw.strip_prefix(":86:") {
+            match lines.last_mut() {
+                Some(last) if last.info.is_none() => last.info = Some(value.to_owned()),
+                _ => warn!("Skipping :86: without statement line"),
+            }
+            field = Some("86");
+        } else if let Some(value) = raw.strip_prefix(":25:") {
+            account = Some(value.trim().to_owned()).filter(|a| !a.is_empty());
+            field = None;
+        } else if raw.starts_with(':') || raw.starts_with('-') {
+            // any other field or the end of a message
+            field = None;
+        } else if let Some(last) = lines.last_mut() {
+            match field {
+                Some("61") => last.line.push_str(raw),
+                Some("86") => {
+                    if let Some(info) = last.info.as_mut() {
+                        info.push_str(raw);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if lines.is_empty() && !input.contains(":20:") {
+        return Err(ImportError("Not an MT940 file".to_owned()));
+    }
+
+    let mut items = Vec::new();
+    for line in lines {
+        if let Some(item) = to_item(line, credits_as_income)? {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+fn to_item(line: StatementLine, credits_as_income: bool) -> Result<Option<FinItem>, ImportError> {
+    let s = line.line.as_str();
+    let invalid = || ImportError(format!("Invalid statement line {s}"));
+
+    // value date YYMMDD, optional booking date MMDD
+    let value_date = s
+        .get(..6)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%y%m%d").ok())
+        .ok_or_else(invalid)?;
+    let mut rest = s.get(6..).ok_or_else(invalid)?;
+    let mut date = value_date;
+    // the text is user input, slice it at char boundaries only
+    if let Some(mmdd) = rest
+        .get(..4)
+        .filter(|d| d.chars().all(|c| c.is_ascii_digit()))
+    {
+        date = booking_date(value_date, mmdd).ok_or_else(invalid)?;
+        rest = &rest[4..];
+    }
+
+    // debit/credit mark, reversals flip the direction
+    let (credit, mark_len) = if rest.starts_with("RC") {
+        (false, 2)
+    } else if rest.starts_with("RD") {
+        (true, 2)
+    } else if rest.starts_with('C') {
+        (true, 1)
+    } else if rest.starts_with('D') {
+        (false, 1)
+    } else {
+        return Err(invalid());
+    };
+    rest = &rest[mark_len..];
+    // optional funds code (third letter of the currency)
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let amount_len = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ','))
+        .unwrap_or(rest.len());
+    let (amount, tail) = (rest.get(..amount_len), rest.get(amount_len..));
+    let amount = amount
+        .and_then(|a| a.replace(',', ".").parse::<f32>().ok())
+        .ok_or_else(invalid)?;
+    rest = tail.unwrap_or_default();
+
+    // transaction type (e.g. NMSC), then customer reference // bank reference
+    let bank_ref = rest
+        .get(4..)
+        .and_then(|r| r.split_once("//"))
+        .map(|(_, b)| b.trim().to_owned())
+        .filter(|b| !b.is_empty() && b != "NONREF");
+
+    let (name, memo) = match line.info.as_deref().map(parse_info) {
+        Some((Some(name), memo)) => (name, memo),
+        Some((None, Some(memo))) => (memo, None),
+        _ => {
+            warn!("Statement line of {} without information", date);
+            (String::new(), None)
+        }
+    };
+
+    let signed = if credit { amount } else { -amount };
+    let item = bank_item(date, name, signed, credits_as_income).map(|mut item| {
+        item.memo = memo;
+        item.fitid = bank_ref;
+        item.account = line.account;
+        item
+    });
+    Ok(item)
+}
+
+/// The booking date has no year, take the one closest to the value date
+fn booking_date(value_date: NaiveDate, mmdd: &str) -> Option<NaiveDate> {
+    let month = mmdd[..2].parse::<u32>().ok()?;
+    let day = mmdd[2..].parse::<u32>().ok()?;
+    let year = match (value_date.month(), month) {
+        (1, 12) => value_date.year() - 1,
+        (12, 1) => value_date.year() + 1,
+        _ => value_date.year(),
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Split the :86: information into counterparty and remittance info.
+/// Structured information (used by German banks) has ?NN subfields.
+fn parse_info(info: &str) -> (Option<String>, Option<String>) {
+    if !info.contains("?20") && !info.contains("?32") {
+        return (None, non_empty(info));
+    }
+
+    let mut name = String::new();
+    let mut purpose = String::new();
+    for sub in info.split('?').skip(1) {
+        let (Some(code), Some(value)) = (sub.get(..2), sub.get(2..)) else {
+            continue;
+        };
+        match code {
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60" | "61"
+            | "62" | "63" => purpose.push_str(value),
+            "32" | "33" => name.push_str(value),
+            _ => {}
+        }
+    }
+    (non_empty(&name), non_empty(&purpose))
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    Some(s.trim().to_owned()).filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = ":20:STARTUMS
+:25:12345678/0012345678
+:28C:00001/001
+:60F:C230430EUR1000,00
+:61:2305010501DR12,50NMSCNONREF//B123
+:86:020?00Kartenzahlung?20Coffee?32Cafe Anna
+:61:230502C2500,NMSCNONREF
+:86:166?00Gutschrift?32Employer
+:62F:C230502EUR3487,50
+-";
+
+    #[test]
+    fn parses_statement_lines() {
+        let items = parse(STATEMENT, false).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].date, NaiveDate::from_ymd_opt(2023, 5, 1).unwrap());
+        assert_eq!(items[0].price, 12.5);
+        assert_eq!(items[0].fitid.as_deref(), Some("B123"));
+        assert_eq!(items[0].account.as_deref(), Some("12345678/0012345678"));
+
+        let items = parse(STATEMENT, true).unwrap();
+        assert_eq!(items[1].price, -2500.0);
+    }
+
+    #[test]
+    fn fails_on_text_in_statement_lines() {
+        for line in [
+            ":61:230501aa\u{20ac}D12,50",
+            ":61:230501D\u{e4}",
+            ":61:23050\u{e4}",
+        ] {
+            assert!(parse(line, false).is_err(), "{}", line);
+        }
+    }
+}
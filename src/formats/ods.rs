@@ -0,0 +1,95 @@
+use crate::common::escape_xml;
+use crate::formats::spreadsheet::{zip_files, Cell, Sheet};
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+<manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>"#;
+
+const CONTENT_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:number="urn:oasis:names:tc:opendocument:xmlns:datastyle:1.0" office:version="1.2">
+<office:automatic-styles>
+<number:date-style style:name="date"><number:year number:style="long"/><number:text>-</number:text><number:month number:style="long"/><number:text>-</number:text><number:day number:style="long"/></number:date-style>
+<style:style style:name="cdate" style:family="table-cell" style:data-style-name="date"/>
+<style:style style:name="cheader" style:family="table-cell"><style:text-properties fo:font-weight="bold"/></style:style>
+</office:automatic-styles>
+<office:body><office:spreadsheet>"#;
+
+/// Write the sheets as an OpenDocument spreadsheet
+pub(crate) fn write(sheets: &[Sheet]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut content = CONTENT_HEAD.to_owned();
+    for sheet in sheets {
+        content.push_str(&format!(
+            r#"<table:table table:name="{}">"#,
+            escape_xml(&sheet.name)
+        ));
+        for (r, row) in sheet.rows.iter().enumerate() {
+            content.push_str("<table:table-row>");
+            for cell in row {
+                match cell {
+                    // the header row is bold
+                    Cell::Text(text) if r == 0 => content.push_str(&format!(
+                        r#"<table:table-cell table:style-name="cheader" office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+                        escape_xml(text)
+                    )),
+                    Cell::Text(text) => content.push_str(&format!(
+                        r#"<table:table-cell office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+                        escape_xml(text)
+                    )),
+                    Cell::Number(number) => content.push_str(&format!(
+                        r#"<table:table-cell office:value-type="float" office:value="{number}"><text:p>{number}</text:p></table:table-cell>"#
+                    )),
+                    Cell::Date(date) => content.push_str(&format!(
+                        r#"<table:table-cell table:style-name="cdate" office:value-type="date" office:date-value="{date}"><text:p>{date}</text:p></table:table-cell>"#
+                    )),
+                }
+            }
+            content.push_str("</table:table-row>");
+        }
+        content.push_str("</table:table>");
+    }
+    content.push_str("</office:spreadsheet></office:body></office:document-content>");
+
+    // the mimetype has to be the first, uncompressed file
+    zip_files(&[
+        ("mimetype", MIMETYPE.to_owned(), true),
+        ("META-INF/manifest.xml", MANIFEST.to_owned(), false),
+        ("content.xml", content, false),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::spreadsheet::tests::{sheets, unzip};
+
+    #[test]
+    fn writes_the_mimetype_first() {
+        let bytes = write(&sheets()).unwrap();
+        // readers look for the uncompressed mimetype at a fixed offset
+        assert_eq!(&bytes[30..38], b"mimetype");
+        assert_eq!(&bytes[38..38 + MIMETYPE.len()], MIMETYPE.as_bytes());
+
+        let files = unzip(bytes);
+        let names: Vec<&str> = files.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["mimetype", "META-INF/manifest.xml", "content.xml"]);
+    }
+
+    #[test]
+    fn writes_the_cell_values() {
+        let files = unzip(write(&sheets()).unwrap());
+        let content = &files[2].1;
+        assert_eq!(content.matches("<table:table ").count(), 4);
+        assert!(content.contains(r#"<table:table table:name="May 2023">"#));
+        assert!(content.contains(
+            r#"office:value-type="date" office:date-value="2023-05-01"><text:p>2023-05-01</text:p>"#
+        ));
+        assert!(content.contains(r#"<text:p>Rent &amp; more</text:p>"#));
+        assert!(content.contains(
+            r#"<table:table-cell office:value-type="float" office:value="400"><text:p>400</text:p></table:table-cell>"#
+        ));
+    }
+}
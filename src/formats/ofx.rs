@@ -0,0 +1,206 @@
+use chrono::NaiveDate;
+use log::warn;
+
+use crate::formats::{bank_item, ImportError};
+use crate::model::FinItem;
+
+/// A single STMTTRN record
+#[derive(Default)]
+struct Transaction {
+    posted: Option<String>,
+    amount: Option<String>,
+    fitid: Option<String>,
+    name: Option<String>,
+    memo: Option<String>,
+}
+
+/// Parse an OFX/QFX bank statement (SGML 1.x or XML 2.x) into items
+///
+/// Debits become items with a positive price, credits are skipped unless
+/// `credits_as_income` is set.
+pub(crate) fn parse(input: &str, credits_as_income: bool) -> Result<Vec<FinItem>, ImportError> {
+    if !input.contains("<OFX>") {
+        return Err(ImportError("Not an OFX file".to_owned()));
+    }
+
+    let mut items = Vec::new();
+    let mut account: Option<String> = None;
+    let mut current: Option<Transaction> = None;
+    // every chunk starts with a tag, followed by its (possibly empty) value
+    for chunk in input.split('<').skip(1) {
+        let Some((tag, value)) = chunk.split_once('>') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match tag.trim().to_uppercase().as_str() {
+            "STMTTRN" => current = Some(Transaction::default()),
+            "/STMTTRN" => {
+                if let Some(trn) = current.take() {
+                    if let Some(mut item) = to_item(trn, credits_as_income)? {
+                        item.account = account.clone();
+                        items.push(item);
+                    }
+                }
+            }
+            "ACCTID" if !value.is_empty() => account = Some(unescape(value)),
+            tag => {
+                let Some(trn) = current.as_mut() else {
+                    continue;
+                };
+                if value.is_empty() {
+                    continue;
+                }
+                let value = Some(unescape(value));
+                match tag {
+                    "DTPOSTED" => trn.posted = value,
+                    "TRNAMT" => trn.amount = value,
+                    "FITID" => trn.fitid = value,
+                    "NAME" => trn.name = value,
+                    "MEMO" => trn.memo = value,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+fn to_item(trn: Transaction, credits_as_income: bool) -> Result<Option<FinItem>, ImportError> {
+    let posted = trn
+        .posted
+        .ok_or_else(|| ImportError("Transaction without DTPOSTED".to_owned()))?;
+    // YYYYMMDD[HHMMSS[.XXX]][TZ], only the date is relevant
+    let date = posted
+        .get(..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .ok_or_else(|| ImportError(format!("Invalid DTPOSTED {posted}")))?;
+    let amount = trn
+        .amount
+        .as_deref()
+        .and_then(|a| a.replace(',', ".").parse::<f32>().ok())
+        .ok_or_else(|| ImportError(format!("Invalid TRNAMT in transaction of {date}")))?;
+
+    let (name, memo) = match (trn.name, trn.memo) {
+        (Some(name), memo) => (name, memo),
+        (None, Some(memo)) => (memo, None),
+        (None, None) => {
+            warn!("Transaction of {} without NAME or MEMO", date);
+            (String::new(), None)
+        }
+    };
+
+    let item = bank_item(date, name, amount, credits_as_income).map(|mut item| {
+        item.memo = memo;
+        item.fitid = trn.fitid;
+        item
+    });
+    Ok(item)
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// OFX 1.x, SGML without closing tags on the values
+    const OFX: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<BANKACCTFROM><BANKID>10020030<ACCTID>0012345678<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20230501120000[+2:CEST]
+<TRNAMT>-12.50
+<FITID>T-1
+<NAME>Bakery Smith &amp; Sons
+<MEMO>Card payment
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20230502
+<TRNAMT>2500,00
+<FITID>T-2
+<NAME>Employer
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>";
+
+    /// QFX as written by Quicken, OFX 2.x XML
+    const QFX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="211" SECURITY="NONE"?>
+<OFX>
+  <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+    <CCACCTFROM><ACCTID>4111000011112222</ACCTID></CCACCTFROM>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20230503</DTPOSTED>
+        <TRNAMT>-40.00</TRNAMT>
+        <FITID>2023050301</FITID>
+        <MEMO>Fuel</MEMO>
+      </STMTTRN>
+      <STMTTRN>
+        <TRNTYPE>CREDIT</TRNTYPE>
+        <DTPOSTED>20230504</DTPOSTED>
+        <TRNAMT>15.00</TRNAMT>
+        <FITID>2023050402</FITID>
+        <NAME>Refund</NAME>
+      </STMTTRN>
+    </BANKTRANLIST>
+  </CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>"#;
+
+    #[test]
+    fn parses_sgml_ofx() {
+        let items = parse(OFX, false).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].date, NaiveDate::from_ymd_opt(2023, 5, 1).unwrap());
+        assert_eq!(items[0].item, "Bakery Smith & Sons");
+        assert_eq!(items[0].memo.as_deref(), Some("Card payment"));
+        assert_eq!(items[0].price, 12.5);
+        assert_eq!(items[0].fitid.as_deref(), Some("T-1"));
+        assert_eq!(items[0].account.as_deref(), Some("0012345678"));
+
+        let items = parse(OFX, true).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].price, -2500.0);
+        assert_eq!(items[1].fitid.as_deref(), Some("T-2"));
+    }
+
+    #[test]
+    fn parses_xml_qfx() {
+        let items = parse(QFX, true).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].item, "Fuel");
+        assert_eq!(items[0].memo, None);
+        assert_eq!(items[0].price, 40.0);
+        assert_eq!(items[0].fitid.as_deref(), Some("2023050301"));
+        assert_eq!(items[1].item, "Refund");
+        assert_eq!(items[1].price, -15.0);
+        assert_eq!(items[1].fitid.as_deref(), Some("2023050402"));
+        assert_eq!(items[1].account.as_deref(), Some("4111000011112222"));
+    }
+
+    #[test]
+    fn fails_without_a_date() {
+        let input = "<OFX><STMTTRN><TRNAMT>-1.00</STMTTRN></OFX>";
+        assert!(parse(input, false).is_err());
+        assert!(parse("OFXHEADER:100", false).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::NaiveDate;
+use log::warn;
+
+use crate::formats::{bank_item, parse_amount, ImportError};
+use crate::model::FinItem;
+
+/// Date formats seen in the wild, after normalizing `'` and spaces
+const DATE_FORMATS: [&str; 5] = ["%m/%d/%y", "%m/%d/%Y", "%d.%m.%Y", "%Y-%m-%d", "%d/%m/%Y"];
+
+/// A single record, terminated by `^`
+#[derive(Default)]
+struct Record {
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    category: Option<String>,
+    memo: Option<String>,
+}
+
+/// Parse the bank and cash records of a QIF file into items
+///
+/// Debits become items with a positive price, credits are skipped unless
+/// `credits_as_income` is set.
+pub(crate) fn parse(input: &str, credits_as_income: bool) -> Result<Vec<FinItem>, ImportError> {
+    let mut items = Vec::new();
+    let mut record = Record::default();
+    // records before the first header are treated as bank records
+    let mut supported = true;
+
+    for line in input.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.to_lowercase();
+            if header.starts_with("type:") {
+                supported = matches!(
+                    header.trim_start_matches("type:"),
+                    "bank" | "cash" | "ccard"
+                );
+                if !supported {
+                    warn!("Skipping unsupported QIF section {}", line);
+                }
+            }
+            continue;
+        }
+        if !supported {
+            continue;
+        }
+
+        let mut chars = line.chars();
+        let code = chars.next();
+        let value = Some(chars.as_str().trim().to_owned());
+        match code {
+            Some('D') => record.date = value,
+            Some('T') | Some('U') => record.amount = value,
+            Some('P') => record.payee = value,
+            Some('L') => record.category = value,
+            Some('M') => record.memo = value,
+            Some('^') => {
+                if let Some(item) = to_item(std::mem::take(&mut record), credits_as_income)? {
+                    items.push(item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(items)
+}
+
+/// Write items as a QIF bank account
+pub(crate) fn write(items: &[FinItem]) -> String {
+    let mut out = String::from("!Type:Bank\n");
+    for item in items {
+        out.push_str(&format!("D{}\n", item.date.format("%m/%d/%Y")));
+        out.push_str(&format!("T{:.2}\n", -item.price));
+        out.push_str(&format!("P{}\n", item.item));
+        if let Some(category) = &item.category {
+            out.push_str(&format!("L{category}\n"));
+        }
+        if let Some(memo) = &item.memo {
+            out.push_str(&format!("M{memo}\n"));
+        }
+        out.push_str("^\n");
+    }
+    out
+}
+
+fn to_item(record: Record, credits_as_income: bool) -> Result<Option<FinItem>, ImportError> {
+    let raw_date = record
+        .date
+        .ok_or_else(|| ImportError("Record without date".to_owned()))?;
+    let date =
+        parse_date(&raw_date).ok_or_else(|| ImportError(format!("Invalid date {raw_date}")))?;
+    let amount = record
+        .amount
+        .as_deref()
+        .and_then(|a| parse_amount(a).ok())
+        .ok_or_else(|| ImportError(format!("Invalid amount in record of {date}")))?;
+
+    let Some(mut item) = bank_item(
+        date,
+        record.payee.unwrap_or_default(),
+        amount,
+        credits_as_income,
+    ) else {
+        return Ok(None);
+    };
+    // transfers are written as [Account]
+    if let Some(category) = record.category.filter(|c| !c.starts_with('[')) {
+        item.category = Some(category);
+    }
+    item.memo = record.memo;
+    Ok(Some(item))
+}
+
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    // Quicken writes e.g. " 1/ 5'23" for dates after 2000
+    let normalized = raw.replace(' ', "").replace('\'', "/");
+    DATE_FORMATS
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(&normalized, f).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bank_records() {
+        let input = "!Type:Bank\nD05/01/2023\nT-12,50\nPCoffee\nLFood\n^\n\
+                     D05/02'2023\nT-1,234.56\nPRent\nL[Savings]\nMMay\n^\n\
+                     D05/03/2023\nT2500.00\nPSalary\n^\n\
+                     D05/04/2023\nT-1,500\nPLaptop\n^\n";
+        let items = parse(input, false).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].item, "Coffee");
+        assert_eq!(items[0].price, 12.5);
+        assert_eq!(items[0].category.as_deref(), Some("Food"));
+        assert_eq!(items[1].date, NaiveDate::from_ymd_opt(2023, 5, 2).unwrap());
+        assert_eq!(items[1].price, 1234.56);
+        assert_eq!(items[1].memo.as_deref(), Some("May"));
+        assert_eq!(items[2].price, 1500.0);
+
+        let items = parse(input, true).unwrap();
+        assert_eq!(items[2].price, -2500.0);
+    }
+
+    #[test]
+    fn skips_unsupported_sections() {
+        let input = "!Type:Invst\nD05/01/2023\nT-12.50\n^\n";
+        assert!(parse(input, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fails_on_invalid_amounts() {
+        assert!(parse("D05/01/2023\nTtwelve\n^\n", false).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::NaiveDate;
+use log::{debug, warn};
+
+use crate::formats::{owed_by, parse_amount, quote_field, split_fields, ImportError, COMMODITY};
+use crate::model::FinItem;
+
+/// Columns before the per-person balance columns
+const FIXED_COLUMNS: [&str; 5] = ["Date", "Description", "Category", "Cost", "Currency"];
+
+/// Parse a Splitwise group export into items
+///
+/// Each person column holds the net effect on that person's balance, so the
+/// payer is the person with the largest positive value and the ratio is the
+/// part of the cost the others owe them. Payments between people are skipped.
+pub(crate) fn parse(input: &str) -> Result<Vec<FinItem>, ImportError> {
+    let mut lines = input.lines().filter(|l| !l.trim().is_empty());
+    let header = split_fields(lines.next().unwrap_or_default(), ',');
+    if header.len() <= FIXED_COLUMNS.len() || header[..FIXED_COLUMNS.len()] != FIXED_COLUMNS {
+        return Err(ImportError("Not a Splitwise export".to_owned()));
+    }
+    let people = &header[FIXED_COLUMNS.len()..];
+
+    let mut items = Vec::new();
+    for line in lines {
+        let fields = split_fields(line, ',');
+        if fields.len() != header.len() {
+            return Err(ImportError(format!("Invalid line {line}")));
+        }
+        // the last line holds the total balances without a date or cost
+        let Ok(date) = fields[0].trim().parse::<NaiveDate>() else {
+            debug!("Skipping line {}", line);
+            continue;
+        };
+        if fields[3].trim().is_empty() {
+            debug!("Skipping line {}", line);
+            continue;
+        }
+        if fields[2] == "Payment" {
+            debug!("Skipping payment on {}", date);
+            continue;
+        }
+
+        let cost = parse_amount(&fields[3])?;
+        let balances = fields[FIXED_COLUMNS.len()..]
+            .iter()
+            .map(|f| parse_amount(f))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some((payer, owed)) = balances
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+        else {
+            continue;
+        };
+        if *owed <= 0.0 {
+            warn!("Skipping expense on {} without payer", date);
+            continue;
+        }
+
+        let mut item = FinItem::new(date, fields[1].clone(), cost);
+        if !fields[2].is_empty() {
+            item.category = Some(fields[2].clone());
+        }
+        item.owner = people[payer].clone();
+        item.ratio = if cost != 0.0 { owed / cost } else { 0.0 };
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Write shared items in the Splitwise export layout
+pub(crate) fn write(items: &[FinItem], people: &[String]) -> String {
+    let mut out = FIXED_COLUMNS.join(",");
+    for person in people {
+        out.push(',');
+        out.push_str(&quote_field(person, ','));
+    }
+    out.push('\n');
+
+    for item in items.iter().filter(|i| i.ratio > 0.0) {
+        let owed = owed_by(item, people);
+        let category = item.category.as_deref().unwrap_or("General");
+        out.push_str(&format!(
+            "{},{},{},{:.2},{}",
+            item.date,
+            quote_field(&item.item, ','),
+            quote_field(category, ','),
+            item.price,
+            COMMODITY
+        ));
+        for person in people {
+            let balance = if *person == item.owner {
+                owed.iter().map(|(_, share)| share).sum()
+            } else {
+                owed.iter()
+                    .find(|(p, _)| p == person)
+                    .map_or(0.0, |(_, share)| -share)
+            };
+            out.push_str(&format!(",{balance:.2}"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn people() -> Vec<String> {
+        vec!["anna".to_owned(), "ben".to_owned(), "carl".to_owned()]
+    }
+
+    fn item(name: &str, price: f32, owner: &str, ratio: f32) -> FinItem {
+        let mut item = FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        );
+        item.owner = owner.to_owned();
+        item.ratio = ratio;
+        item
+    }
+
+    #[test]
+    fn writes_the_owed_shares() {
+        let items = [
+            item("Dinner", 90.0, "anna", 2.0 / 3.0),
+            item("Own", 5.0, "ben", 0.0),
+        ];
+        let written = write(&items, &people());
+        assert_eq!(
+            written,
+            "Date,Description,Category,Cost,Currency,anna,ben,carl\n\
+             2023-05-01,Dinner,category,90.00,EUR,60.00,-30.00,-30.00\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_shared_items() {
+        let items = [
+            item("Dinner", 90.0, "anna", 2.0 / 3.0),
+            item("Taxi, airport", 30.0, "carl", 0.5),
+        ];
+        let parsed = parse(&write(&items, &people())).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (parsed, item) in parsed.iter().zip(&items) {
+            assert_eq!(parsed.item, item.item);
+            assert_eq!(parsed.price, item.price);
+            assert_eq!(parsed.owner, item.owner);
+            assert!((parsed.ratio - item.ratio).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn skips_payments_and_the_total() {
+        let input = "Date,Description,Category,Cost,Currency,anna,ben
+2023-05-01,Groceries,General,40.00,EUR,20.00,-20.00
+2023-05-02,Settle up,Payment,20.00,EUR,-20.00,20.00
+
+,Total balance,,,EUR,0.00,0.00";
+        let items = parse(input).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].category.as_deref(), Some("General"));
+        assert_eq!(items[0].ratio, 0.5);
+        assert!(parse("Date,Description\n").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use chrono::{Datelike, NaiveDate};
+use itertools::Itertools;
+use std::io::{Cursor, Write};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::common::{paid_by_owner, spent_by_category, to_name};
+use crate::model::FinItem;
+
+/// A spreadsheet cell
+pub(crate) enum Cell {
+    Text(String),
+    Number(f32),
+    Date(NaiveDate),
+}
+
+/// A named sheet, the first row is the header
+pub(crate) struct Sheet {
+    pub(crate) name: String,
+    pub(crate) rows: Vec<Vec<Cell>>,
+}
+
+/// One sheet per month with the columns of the central table, followed by
+/// per-person and per-category totals for each month
+pub(crate) fn workbook(items: &[FinItem]) -> Vec<Sheet> {
+    let months = items
+        .iter()
+        .sorted_by_key(|i| i.date)
+        .group_by(|i| (i.date.year(), i.date.month()));
+
+    let mut sheets = Vec::new();
+    let mut persons = Sheet {
+        name: "Persons".to_owned(),
+        rows: vec![header(&["Month", "Name", "Paid", "Owed"])],
+    };
+    let mut categories = Sheet {
+        name: "Categories".to_owned(),
+        rows: vec![header(&["Month", "Category", "Paid"])],
+    };
+
+    for ((year, month), group) in &months {
+        let month_items: Vec<FinItem> = group.cloned().collect();
+        let month_name = format!("{} {}", to_name(month), year);
+
+        let mut rows = vec![header(&[
+            "Date", "Item", "Category", "Price", "Name", "Ratio", "Total",
+        ])];
+        for item in &month_items {
+            rows.push(vec![
+                Cell::Date(item.date),
+                Cell::Text(item.item.clone()),
+                Cell::Text(item.category.clone().unwrap_or_else(|| "None".to_owned())),
+                Cell::Number(item.price),
+                Cell::Text(item.owner.clone()),
+                Cell::Number(item.ratio),
+                Cell::Number(item.price * item.ratio),
+            ]);
+        }
+
+        let paid_dict = paid_by_owner(&month_items);
+        for key in paid_dict.keys().sorted() {
+            persons.rows.push(vec![
+                Cell::Text(month_name.clone()),
+                Cell::Text(key.clone()),
+                Cell::Number(paid_dict[key].0),
+                Cell::Number(paid_dict[key].1),
+            ]);
+        }
+        let cat_dict = spent_by_category(&month_items);
+        for key in cat_dict.keys().sorted() {
+            categories.rows.push(vec![
+                Cell::Text(month_name.clone()),
+                Cell::Text(key.clone()),
+                Cell::Number(cat_dict[key]),
+            ]);
+        }
+
+        sheets.push(Sheet {
+            name: month_name,
+            rows,
+        });
+    }
+
+    sheets.push(persons);
+    sheets.push(categories);
+    sheets
+}
+
+fn header(names: &[&str]) -> Vec<Cell> {
+    names.iter().map(|n| Cell::Text(n.to_string())).collect()
+}
+
+/// Zip the named files, stored files are written uncompressed
+pub(crate) fn zip_files(files: &[(&str, String, bool)]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents, stored) in files {
+        let method = if *stored {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+        zip.start_file(*name, FileOptions::default().compression_method(method))?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    /// Names and contents of the zipped files, in order
+    pub(crate) fn unzip(bytes: Vec<u8>) -> Vec<(String, String)> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut contents = String::new();
+                file.read_to_string(&mut contents).unwrap();
+                (file.name().to_owned(), contents)
+            })
+            .collect()
+    }
+
+    /// Rent paid by MB in May, power by anna in May and June
+    pub(crate) fn sheets() -> Vec<Sheet> {
+        let mut rent = FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            "Rent & more".to_owned(),
+            800.0,
+        );
+        rent.category = Some("bills".to_owned());
+        let mut power = rent.clone();
+        power.item = "Power".to_owned();
+        power.price = 60.0;
+        power.owner = "anna".to_owned();
+        let mut june = power.clone();
+        june.date = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+        workbook(&[june, rent, power])
+    }
+
+    #[test]
+    fn has_a_sheet_per_month_and_the_totals() {
+        let sheets = sheets();
+        let names: Vec<&str> = sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["May 2023", "June 2023", "Persons", "Categories"]);
+        assert_eq!(sheets[0].rows.len(), 3);
+        assert!(matches!(&sheets[0].rows[1][1], Cell::Text(t) if t == "Rent & more"));
+        assert!(matches!(sheets[0].rows[1][6], Cell::Number(n) if n == 400.0));
+
+        // May for anna and MB, June for anna
+        let persons = &sheets[2].rows;
+        assert_eq!(persons.len(), 4);
+        assert!(matches!(&persons[1][1], Cell::Text(t) if t == "MB"));
+        assert!(matches!(persons[1][2], Cell::Number(n) if n == 800.0));
+        let categories = &sheets[3].rows;
+        assert_eq!(categories.len(), 3);
+        assert!(matches!(categories[1][2], Cell::Number(n) if n == 860.0));
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use log::debug;
+
+use crate::formats::{owed_by, parse_amount, quote_field, split_fields, ImportError, COMMODITY};
+use crate::model::FinItem;
+
+/// Prefixes of the per-person share columns, newer exports use "Paid for"
+const SHARE_PREFIXES: [&str; 2] = ["Paid for ", "Impacted to "];
+
+/// Parse a Tricount export into items
+///
+/// The payer becomes the owner and the shares of everybody else make up the ratio.
+/// Money transfers between people are skipped.
+pub(crate) fn parse(input: &str) -> Result<Vec<FinItem>, ImportError> {
+    let mut lines = input.lines().filter(|l| !l.trim().is_empty());
+    let header = split_fields(lines.next().unwrap_or_default(), ',');
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.trim()));
+
+    let (Some(date_col), Some(title_col), Some(amount_col), Some(payer_col)) = (
+        column(&["Date & time", "Date"]),
+        column(&["Title", "Name", "Description"]),
+        column(&["Amount"]),
+        column(&["Paid by"]),
+    ) else {
+        return Err(ImportError("Not a Tricount export".to_owned()));
+    };
+    let type_col = column(&["Type", "Transaction type"]);
+    let category_col = column(&["Category"]);
+    let shares: Vec<(usize, &str)> = header
+        .iter()
+        .enumerate()
+        .filter_map(|(i, h)| {
+            SHARE_PREFIXES
+                .iter()
+                .find_map(|p| h.trim().strip_prefix(p))
+                .map(|person| (i, person))
+        })
+        .collect();
+
+    let mut items = Vec::new();
+    for line in lines {
+        let fields = split_fields(line, ',');
+        if fields.len() != header.len() {
+            return Err(ImportError(format!("Invalid line {line}")));
+        }
+        if let Some(kind) = type_col.map(|c| fields[c].to_lowercase()) {
+            if kind.contains("transfer") || kind.contains("income") {
+                debug!("Skipping {} {}", kind, fields[title_col]);
+                continue;
+            }
+        }
+
+        let date = parse_date(&fields[date_col])
+            .ok_or_else(|| ImportError(format!("Invalid date {}", fields[date_col])))?;
+        // newer exports write expenses as negative amounts
+        let amount = parse_amount(&fields[amount_col])?.abs();
+        let payer = fields[payer_col].trim().to_owned();
+        let payer_share = shares
+            .iter()
+            .find(|(_, person)| *person == payer)
+            .map_or(Ok(0.0), |(c, _)| parse_amount(&fields[*c]))?
+            .abs();
+
+        let mut item = FinItem::new(date, fields[title_col].clone(), amount);
+        if let Some(category) = category_col.map(|c| &fields[c]).filter(|c| !c.is_empty()) {
+            item.category = Some(category.clone());
+        }
+        item.owner = payer;
+        item.ratio = if amount != 0.0 {
+            (amount - payer_share) / amount
+        } else {
+            0.0
+        };
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Write shared items in the Tricount export layout
+pub(crate) fn write(items: &[FinItem], people: &[String]) -> String {
+    let mut out = String::from("Date & time,Type,Title,Amount,Currency,Category,Paid by");
+    for person in people {
+        out.push(',');
+        out.push_str(&quote_field(
+            &format!("{}{}", SHARE_PREFIXES[0], person),
+            ',',
+        ));
+    }
+    out.push('\n');
+
+    for item in items.iter().filter(|i| i.ratio > 0.0) {
+        let owed = owed_by(item, people);
+        out.push_str(&format!(
+            "{} 00:00,Expense,{},{:.2},{},{},{}",
+            item.date,
+            quote_field(&item.item, ','),
+            item.price,
+            COMMODITY,
+            quote_field(item.category.as_deref().unwrap_or_default(), ','),
+            quote_field(&item.owner, ',')
+        ));
+        for person in people {
+            let share = if *person == item.owner {
+                item.price * (1.0 - item.ratio)
+            } else {
+                owed.iter()
+                    .find(|(p, _)| p == person)
+                    .map_or(0.0, |(_, share)| *share)
+            };
+            out.push_str(&format!(",{share:.2}"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn parse_date(field: &str) -> Option<NaiveDate> {
+    let field = field.trim();
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%d/%m/%Y %H:%M"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(field, f).ok())
+        .map(|dt| dt.date())
+        .or_else(|| {
+            ["%Y-%m-%d", "%d/%m/%Y"]
+                .iter()
+                .find_map(|f| NaiveDate::parse_from_str(field, f).ok())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn people() -> Vec<String> {
+        vec!["anna".to_owned(), "ben".to_owned(), "carl".to_owned()]
+    }
+
+    fn item(name: &str, price: f32, owner: &str, ratio: f32) -> FinItem {
+        let mut item = FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        );
+        item.owner = owner.to_owned();
+        item.ratio = ratio;
+        item
+    }
+
+    #[test]
+    fn writes_the_shares() {
+        let items = [item("Dinner", 90.0, "anna", 2.0 / 3.0)];
+        let written = write(&items, &people());
+        assert_eq!(
+            written,
+            "Date & time,Type,Title,Amount,Currency,Category,Paid by,\
+             Paid for anna,Paid for ben,Paid for carl\n\
+             2023-05-01 00:00,Expense,Dinner,90.00,EUR,category,anna,30.00,30.00,30.00\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_shared_items() {
+        let items = [
+            item("Dinner", 90.0, "anna", 2.0 / 3.0),
+            item("Taxi, airport", 30.0, "carl", 0.5),
+        ];
+        let parsed = parse(&write(&items, &people())).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (parsed, item) in parsed.iter().zip(&items) {
+            assert_eq!(parsed.date, item.date);
+            assert_eq!(parsed.item, item.item);
+            assert_eq!(parsed.price, item.price);
+            assert_eq!(parsed.owner, item.owner);
+            assert!((parsed.ratio - item.ratio).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn parses_older_exports() {
+        let input = "Date,Type,Name,Amount,Paid by,Impacted to anna,Impacted to ben
+01/05/2023,Expense,Groceries,-40.00,anna,-10.00,-30.00
+02/05/2023,Money transfer,Settle up,30.00,ben,30.00,0.00";
+        let items = parse(input).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].date, NaiveDate::from_ymd_opt(2023, 5, 1).unwrap());
+        assert_eq!(items[0].price, 40.0);
+        assert_eq!(items[0].ratio, 0.75);
+    }
+}
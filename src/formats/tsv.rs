@@ -0,0 +1,229 @@
+use chrono::NaiveDate;
+use log::warn;
+
+use crate::formats::{parse_amount, quote_field, split_fields, ImportError};
+use crate::model::FinItem;
+
+const HEADER: [&str; 6] = ["Date", "Item", "Category", "Price", "Name", "Ratio"];
+
+const DATE_FORMATS: [&str; 4] = ["%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y", "%Y/%m/%d"];
+
+/// Which column holds which field, if any
+#[derive(Default)]
+struct Columns {
+    date: Option<usize>,
+    item: Option<usize>,
+    category: Option<usize>,
+    price: Option<usize>,
+    owner: Option<usize>,
+    ratio: Option<usize>,
+}
+
+/// Parse tab separated rows, e.g. copied from a spreadsheet, into items
+///
+/// The columns are taken from a header row if there is one, otherwise they are
+/// guessed from the cell contents. Missing fields get the defaults of a new item.
+pub(crate) fn parse(input: &str) -> Result<Vec<FinItem>, ImportError> {
+    let mut rows: Vec<Vec<String>> = input
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| split_fields(l, '\t'))
+        .collect();
+
+    let columns = match rows.first().and_then(|r| header_columns(r)) {
+        Some(columns) => {
+            rows.remove(0);
+            columns
+        }
+        None => guess_columns(&rows),
+    };
+    let Some(price_col) = columns.price else {
+        return Err(ImportError("No price column found".to_owned()));
+    };
+
+    let today = chrono::offset::Local::now().date_naive();
+    let cell = |row: &[String], col: Option<usize>| {
+        col.and_then(|c| row.get(c))
+            .map(|c| c.trim().to_owned())
+            .filter(|c| !c.is_empty())
+    };
+
+    let mut items = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let Some(price) = cell(row, Some(price_col)).and_then(|p| parse_number(&p)) else {
+            warn!("Skipping row {} without a price", i + 1);
+            continue;
+        };
+        let date = match cell(row, columns.date) {
+            Some(date) => match parse_date(&date) {
+                Some(date) => date,
+                None => {
+                    warn!("Skipping row {} with invalid date {}", i + 1, date);
+                    continue;
+                }
+            },
+            None => today,
+        };
+
+        let mut item = FinItem::new(date, cell(row, columns.item).unwrap_or_default(), price);
+        if let Some(category) = cell(row, columns.category) {
+            item.category = Some(category);
+        }
+        if let Some(owner) = cell(row, columns.owner) {
+            item.owner = owner;
+        }
+        if let Some(ratio) = cell(row, columns.ratio).and_then(|r| parse_number(&r)) {
+            item.ratio = ratio.clamp(0.0, 1.0);
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Write items as tab separated rows with a header
+pub(crate) fn write(items: &[FinItem]) -> String {
+    let mut out = HEADER.join("\t");
+    out.push('\n');
+    for item in items {
+        let fields = [
+            item.date.to_string(),
+            item.item.clone(),
+            item.category.clone().unwrap_or_default(),
+            item.price.to_string(),
+            item.owner.clone(),
+            item.ratio.to_string(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| quote_field(f, '\t')).collect();
+        out.push_str(&fields.join("\t"));
+        out.push('\n');
+    }
+    out
+}
+
+/// Columns from a header row, None if the row looks like data
+fn header_columns(row: &[String]) -> Option<Columns> {
+    let mut columns = Columns::default();
+    for (i, name) in row.iter().enumerate() {
+        let slot = match name.trim().to_lowercase().as_str() {
+            "date" | "day" => &mut columns.date,
+            "item" | "description" | "title" | "payee" => &mut columns.item,
+            "category" => &mut columns.category,
+            "price" | "amount" | "cost" | "value" => &mut columns.price,
+            "name" | "owner" | "paid by" | "payer" => &mut columns.owner,
+            "ratio" | "share" => &mut columns.ratio,
+            _ => continue,
+        };
+        slot.get_or_insert(i);
+    }
+    columns.price.map(|_| columns)
+}
+
+/// Guess the columns from the cell contents: the first date column, a
+/// numeric column for the price and one between 0 and 1 for the ratio, and
+/// the text columns in table order
+fn guess_columns(rows: &[Vec<String>]) -> Columns {
+    let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let all = |col: usize, check: &dyn Fn(&str) -> bool| {
+        let mut cells = rows
+            .iter()
+            .filter_map(|r| r.get(col))
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+            .peekable();
+        cells.peek().is_some() && cells.all(check)
+    };
+
+    let mut columns = Columns::default();
+    let mut numeric = Vec::new();
+    let mut text = Vec::new();
+    for col in 0..width {
+        if columns.date.is_none() && all(col, &|c| parse_date(c).is_some()) {
+            columns.date = Some(col);
+        } else if all(col, &|c| parse_number(c).is_some()) {
+            numeric.push(col);
+        } else {
+            text.push(col);
+        }
+    }
+
+    // the ratio only needs its own column if there is a price besides it
+    if numeric.len() > 1 {
+        let is_ratio = |c: &str| parse_number(c).map_or(false, |n| (0.0..=1.0).contains(&n));
+        if let Some(pos) = numeric.iter().rposition(|&col| all(col, &is_ratio)) {
+            columns.ratio = Some(numeric.remove(pos));
+        }
+    }
+    columns.price = numeric.first().copied();
+
+    let mut text = text.into_iter();
+    columns.item = text.next();
+    columns.category = text.next();
+    columns.owner = text.next();
+    columns
+}
+
+fn parse_date(field: &str) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(field, f).ok())
+}
+
+/// Parse a number as written by spreadsheets, with an optional currency sign,
+/// a decimal comma or a percent sign
+fn parse_number(field: &str) -> Option<f32> {
+    let field = field
+        .trim()
+        .trim_start_matches(['€', '$', '£'])
+        .trim_end_matches(['€', '$', '£'])
+        .trim();
+    let (field, scale) = match field.strip_suffix('%') {
+        Some(percent) => (percent.trim(), 0.01),
+        None => (field, 1.0),
+    };
+    parse_amount(field).ok().map(|n| n * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_numbers_of_spreadsheets() {
+        assert_eq!(parse_number("1.234,50"), Some(1234.5));
+        assert_eq!(parse_number("1,234.50 €"), Some(1234.5));
+        assert_eq!(parse_number("€12,50"), Some(12.5));
+        assert_eq!(parse_number("50%"), Some(0.5));
+        assert_eq!(parse_number("Coffee"), None);
+    }
+
+    #[test]
+    fn parses_grouped_integers() {
+        assert_eq!(parse_number("1,234"), Some(1234.0));
+        assert_eq!(parse_number("$1,234"), Some(1234.0));
+        assert_eq!(parse_number("1.234 €"), Some(1234.0));
+
+        let input = "Date\tItem\tPrice\n2023-05-01\tLaptop\t1,234\n";
+        assert_eq!(parse(input).unwrap()[0].price, 1234.0);
+    }
+
+    #[test]
+    fn parses_rows_with_a_header() {
+        let input = "Date\tItem\tPrice\tName\n2023-05-01\tRent\t1.234,50\tAnna\n";
+        let items = parse(input).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item, "Rent");
+        assert_eq!(items[0].price, 1234.5);
+        assert_eq!(items[0].owner, "Anna");
+    }
+
+    #[test]
+    fn guesses_the_columns() {
+        let input = "01.05.2023\tCoffee\t3,20\t50%\n02.05.2023\tBread\t2,10\t50%\n";
+        let items = parse(input).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].date, NaiveDate::from_ymd_opt(2023, 5, 1).unwrap());
+        assert_eq!(items[0].item, "Coffee");
+        assert_eq!(items[1].price, 2.1);
+        assert_eq!(items[1].ratio, 0.5);
+    }
+}
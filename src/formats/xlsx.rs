@@ -0,0 +1,187 @@
+use chrono::NaiveDate;
+
+use crate::common::escape_xml;
+use crate::formats::spreadsheet::{zip_files, Cell, Sheet};
+
+const CONTENT_TYPES_HEAD: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+<Default Extension="xml" ContentType="application/xml"/>
+<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>
+<Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>
+"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>
+</Relationships>"#;
+
+/// Style 1 is a date, style 2 a bold header
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+<fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts>
+<fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills>
+<borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders>
+<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>
+<cellXfs count="3"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="14" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs>
+</styleSheet>"#;
+
+/// Write the sheets as an Office Open XML workbook
+pub(crate) fn write(sheets: &[Sheet]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut content_types = CONTENT_TYPES_HEAD.to_owned();
+    let mut workbook = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+    );
+    let mut workbook_rels = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId0" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#,
+    );
+
+    let mut sheet_files = Vec::new();
+    for (i, sheet) in sheets.iter().enumerate() {
+        let n = i + 1;
+        content_types.push_str(&format!(
+            r#"<Override PartName="/xl/worksheets/sheet{n}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+        ));
+        workbook.push_str(&format!(
+            r#"<sheet name="{}" sheetId="{n}" r:id="rId{n}"/>"#,
+            escape_xml(&sheet_name(&sheet.name))
+        ));
+        workbook_rels.push_str(&format!(
+            r#"<Relationship Id="rId{n}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{n}.xml"/>"#
+        ));
+        sheet_files.push((format!("xl/worksheets/sheet{n}.xml"), worksheet(sheet)));
+    }
+    content_types.push_str("</Types>");
+    workbook.push_str("</sheets></workbook>");
+    workbook_rels.push_str("</Relationships>");
+
+    let mut files = vec![
+        ("[Content_Types].xml", content_types, false),
+        ("_rels/.rels", ROOT_RELS.to_owned(), false),
+        ("xl/workbook.xml", workbook, false),
+        ("xl/_rels/workbook.xml.rels", workbook_rels, false),
+        ("xl/styles.xml", STYLES.to_owned(), false),
+    ];
+    for (name, contents) in &sheet_files {
+        files.push((name.as_str(), contents.clone(), false));
+    }
+    zip_files(&files)
+}
+
+fn worksheet(sheet: &Sheet) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+    );
+    for (r, row) in sheet.rows.iter().enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(c), r + 1);
+            match cell {
+                // the header row is bold
+                Cell::Text(text) if r == 0 => xml.push_str(&format!(
+                    r#"<c r="{reference}" t="inlineStr" s="2"><is><t>{}</t></is></c>"#,
+                    escape_xml(text)
+                )),
+                Cell::Text(text) => xml.push_str(&format!(
+                    r#"<c r="{reference}" t="inlineStr"><is><t>{}</t></is></c>"#,
+                    escape_xml(text)
+                )),
+                Cell::Number(number) => {
+                    xml.push_str(&format!(r#"<c r="{reference}"><v>{number}</v></c>"#))
+                }
+                Cell::Date(date) => xml.push_str(&format!(
+                    r#"<c r="{reference}" s="1"><v>{}</v></c>"#,
+                    serial(*date)
+                )),
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Spreadsheet column name (A, B, ..., Z, AA, ...) of a zero based index
+fn column_name(mut index: usize) -> String {
+    let mut name = String::new();
+    loop {
+        name.insert(0, (b'A' + (index % 26) as u8) as char);
+        if index < 26 {
+            return name;
+        }
+        index = index / 26 - 1;
+    }
+}
+
+/// Days since the spreadsheet epoch
+fn serial(date: NaiveDate) -> i64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30).expect("valid date");
+    (date - epoch).num_days()
+}
+
+/// Sheet names are limited to 31 characters without []:*?/\
+fn sheet_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !"[]:*?/\\".contains(*c))
+        .take(31)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::spreadsheet::tests::{sheets, unzip};
+
+    #[test]
+    fn writes_the_workbook_parts() {
+        let files = unzip(write(&sheets()).unwrap());
+        let names: Vec<&str> = files.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "xl/workbook.xml",
+                "xl/_rels/workbook.xml.rels",
+                "xl/styles.xml",
+                "xl/worksheets/sheet1.xml",
+                "xl/worksheets/sheet2.xml",
+                "xl/worksheets/sheet3.xml",
+                "xl/worksheets/sheet4.xml",
+            ]
+        );
+        assert!(files[0]
+            .1
+            .contains(r#"PartName="/xl/worksheets/sheet4.xml""#));
+        assert!(files[2]
+            .1
+            .contains(r#"<sheet name="May 2023" sheetId="1" r:id="rId1"/>"#));
+    }
+
+    #[test]
+    fn writes_the_cell_values() {
+        let files = unzip(write(&sheets()).unwrap());
+        let may = &files[5].1;
+        assert!(may.contains(r#"<c r="A1" t="inlineStr" s="2"><is><t>Date</t></is></c>"#));
+        // 2023-05-01 as days since the epoch
+        assert!(may.contains(r#"<c r="A2" s="1"><v>45047</v></c>"#));
+        assert!(may.contains(r#"<c r="B2" t="inlineStr"><is><t>Rent &amp; more</t></is></c>"#));
+        assert!(may.contains(r#"<c r="G2"><v>400</v></c>"#));
+    }
+
+    #[test]
+    fn names_columns_and_sheets() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(
+            sheet_name("Trip: Rome/Paris [2023] and then some"),
+            "Trip RomeParis 2023 and then so"
+        );
+    }
+}
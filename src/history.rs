@@ -0,0 +1,361 @@
+use chrono::NaiveDateTime;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::app::TemplateApp;
+use crate::model::FinItem;
+
+/// Seconds between checks for changed items to record
+const RECORD_INTERVAL: f64 = 1.0;
+
+/// Edits of an item by the same person within this many seconds are one change
+const COALESCE_SECONDS: i64 = 60;
+
+/// An item added, edited or deleted, kept in the activity log of the ledger
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+pub struct Change {
+    /// Identity of the change itself, to merge logs of devices
+    pub(crate) id: Uuid,
+    pub(crate) time: NaiveDateTime,
+    /// Who made the change
+    pub(crate) user: String,
+    pub(crate) item: Uuid,
+    /// None if the item was added
+    pub(crate) before: Option<FinItem>,
+    /// None if the item was deleted
+    pub(crate) after: Option<FinItem>,
+}
+
+impl Change {
+    pub(crate) fn kind(&self) -> &'static str {
+        match (&self.before, &self.after) {
+            (None, _) => "Added",
+            (_, None) => "Deleted",
+            _ => "Edited",
+        }
+    }
+
+    /// Name of the item, after the change if it still exists
+    pub(crate) fn item_name(&self) -> &str {
+        self.after
+            .as_ref()
+            .or(self.before.as_ref())
+            .map_or("", |i| i.item.as_str())
+    }
+
+    /// The changed values as "field: before → after", or the values of an
+    /// added or deleted item
+    pub(crate) fn describe(&self) -> String {
+        let (before, after) = match (&self.before, &self.after) {
+            (Some(before), Some(after)) => (before, after),
+            (Some(item), None) | (None, Some(item)) => {
+                return format!(
+                    "{} {} {:.2} ({})",
+                    item.date, item.item, item.price, item.owner
+                )
+            }
+            (None, None) => return String::new(),
+        };
+
+        let fields = [
+            ("date", before.date.to_string(), after.date.to_string()),
+            ("item", before.item.clone(), after.item.clone()),
+            (
+                "category",
+                before.category.clone().unwrap_or_default(),
+                after.category.clone().unwrap_or_default(),
+            ),
+            (
+                "price",
+                format!("{:.2}", before.price),
+                format!("{:.2}", after.price),
+            ),
+            ("name", before.owner.clone(), after.owner.clone()),
+            (
+                "ratio",
+                format!("{:.2}", before.ratio),
+                format!("{:.2}", after.ratio),
+            ),
+            (
+                "memo",
+                before.memo.clone().unwrap_or_default(),
+                after.memo.clone().unwrap_or_default(),
+            ),
+            ("tags", before.tags.join(", "), after.tags.join(", ")),
+        ];
+        fields
+            .iter()
+            .filter(|(_, b, a)| b != a)
+            .map(|(field, b, a)| format!("{field}: {b} → {a}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Who edits on this device, until it is set
+pub(crate) fn default_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "me".to_owned())
+}
+
+impl TemplateApp {
+    /// Record the items changed since the last check. Ledgers opened or
+    /// switched to are not changes, they only start the next check.
+    pub(crate) fn record_changes(&mut self, ctx: &egui::Context) {
+        let time = ctx.input().time;
+        if time - self.record_time < RECORD_INTERVAL {
+            return;
+        }
+        self.record_time = time;
+        self.record_now();
+    }
+
+    pub(crate) fn record_now(&mut self) {
+        let Some(recorded) = &self.recorded else {
+            self.recorded = Some(self.items.clone());
+            return;
+        };
+        if *recorded == self.items {
+            return;
+        }
+
+        let old: HashMap<Uuid, &FinItem> = recorded.iter().map(|i| (i.id, i)).collect();
+        let mut changes: Vec<(Uuid, Option<FinItem>, Option<FinItem>)> = self
+            .items
+            .iter()
+            .filter(|i| old.get(&i.id) != Some(i))
+            .map(|i| (i.id, old.get(&i.id).map(|b| (*b).clone()), Some(i.clone())))
+            .collect();
+        let ids: HashSet<Uuid> = self.items.iter().map(|i| i.id).collect();
+        changes.extend(
+            recorded
+                .iter()
+                .filter(|b| !ids.contains(&b.id))
+                .map(|b| (b.id, Some(b.clone()), None)),
+        );
+
+        let now = chrono::offset::Local::now().naive_local();
+        for (item, before, after) in changes {
+            self.record(item, before, after, now);
+        }
+        self.recorded = Some(self.items.clone());
+    }
+
+    fn record(
+        &mut self,
+        item: Uuid,
+        before: Option<FinItem>,
+        after: Option<FinItem>,
+        now: NaiveDateTime,
+    ) {
+        // typing into a field is one edit
+        let last = self.history.iter().rposition(|c| c.item == item);
+        if let Some(index) = last {
+            let last = &mut self.history[index];
+            let recent = (now - last.time).num_seconds() < COALESCE_SECONDS;
+            if recent && last.user == self.user && last.after.is_some() && after.is_some() {
+                last.after = after;
+                last.time = now;
+                if last.before == last.after {
+                    self.history.remove(index);
+                }
+                return;
+            }
+        }
+
+        self.history.push(Change {
+            id: Uuid::new_v4(),
+            time: now,
+            user: self.user.clone(),
+            item,
+            before,
+            after,
+        });
+    }
+
+    /// Add the changes recorded on another device
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn merge_history(&mut self, theirs: Vec<Change>) {
+        let ours: HashMap<Uuid, usize> = self
+            .history
+            .iter()
+            .enumerate()
+            .map(|(index, c)| (c.id, index))
+            .collect();
+        for change in theirs {
+            match ours.get(&change.id).map(|&index| &mut self.history[index]) {
+                // coalesced with later edits
+                Some(ours) => {
+                    if change.time > ours.time {
+                        *ours = change;
+                    }
+                }
+                None => self.history.push(change),
+            }
+        }
+        self.history.sort_by_key(|c| c.time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+
+    fn item(name: &str, price: f32) -> FinItem {
+        FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        )
+    }
+
+    fn time(seconds: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 5, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            + Duration::seconds(seconds)
+    }
+
+    fn app() -> TemplateApp {
+        let mut app = TemplateApp::default();
+        app.user = "anna".to_owned();
+        app
+    }
+
+    fn priced(item: &FinItem, price: f32) -> Option<FinItem> {
+        Some(FinItem {
+            price,
+            ..item.clone()
+        })
+    }
+
+    #[test]
+    fn records_added_edited_and_deleted_items() {
+        let mut app = app();
+        let rent = item("Rent", 800.0);
+        let power = item("Power", 60.0);
+        app.items = vec![rent.clone(), power.clone()];
+        // the items as opened are no change
+        app.record_now();
+        assert!(app.history.is_empty());
+
+        app.items[0].price = 900.0;
+        app.items.remove(1);
+        app.items.push(item("Gas", 40.0));
+        app.record_now();
+        let kinds: Vec<&str> = app.history.iter().map(Change::kind).collect();
+        assert_eq!(kinds, ["Edited", "Added", "Deleted"]);
+        assert_eq!(app.history[0].describe(), "price: 800.00 → 900.00");
+        assert_eq!(app.history[2].item, power.id);
+
+        app.record_now();
+        assert_eq!(app.history.len(), 3);
+    }
+
+    #[test]
+    fn coalesces_edits_within_the_window() {
+        let mut app = app();
+        let rent = item("Rent", 800.0);
+        app.record(rent.id, Some(rent.clone()), priced(&rent, 810.0), time(0));
+        app.record(
+            rent.id,
+            priced(&rent, 810.0),
+            priced(&rent, 820.0),
+            time(59),
+        );
+        assert_eq!(app.history.len(), 1);
+        assert_eq!(app.history[0].before.as_ref().unwrap().price, 800.0);
+        assert_eq!(app.history[0].after.as_ref().unwrap().price, 820.0);
+        assert!(app.history[0].time == time(59));
+
+        // the window starts again with every edit
+        app.record(
+            rent.id,
+            priced(&rent, 820.0),
+            priced(&rent, 830.0),
+            time(118),
+        );
+        assert_eq!(app.history.len(), 1);
+        app.record(
+            rent.id,
+            priced(&rent, 830.0),
+            priced(&rent, 840.0),
+            time(178),
+        );
+        assert_eq!(app.history.len(), 2);
+    }
+
+    #[test]
+    fn keeps_edits_of_other_users_apart() {
+        let mut app = app();
+        let rent = item("Rent", 800.0);
+        app.record(rent.id, Some(rent.clone()), priced(&rent, 810.0), time(0));
+        app.user = "ben".to_owned();
+        app.record(rent.id, priced(&rent, 810.0), priced(&rent, 820.0), time(1));
+        assert_eq!(app.history.len(), 2);
+        assert_eq!(app.history[1].user, "ben");
+    }
+
+    #[test]
+    fn removes_edits_that_are_undone() {
+        let mut app = app();
+        let rent = item("Rent", 800.0);
+        app.record(rent.id, Some(rent.clone()), priced(&rent, 810.0), time(0));
+        app.record(rent.id, priced(&rent, 810.0), Some(rent.clone()), time(5));
+        assert!(app.history.is_empty());
+    }
+
+    #[test]
+    fn keeps_an_added_and_deleted_item() {
+        let mut app = app();
+        let rent = item("Rent", 800.0);
+        app.record(rent.id, None, Some(rent.clone()), time(0));
+        app.record(rent.id, priced(&rent, 810.0), priced(&rent, 820.0), time(1));
+        assert_eq!(app.history.len(), 1);
+        assert_eq!(app.history[0].kind(), "Added");
+        assert_eq!(app.history[0].after.as_ref().unwrap().price, 820.0);
+
+        app.record(rent.id, priced(&rent, 820.0), None, time(2));
+        let kinds: Vec<&str> = app.history.iter().map(Change::kind).collect();
+        assert_eq!(kinds, ["Added", "Deleted"]);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn merges_the_history_of_other_devices() {
+        let mut app = app();
+        let rent = item("Rent", 800.0);
+        app.record(rent.id, None, Some(rent.clone()), time(0));
+        let mut theirs = app.history.clone();
+        // coalesced with a later edit on their device
+        theirs[0].after = priced(&rent, 820.0);
+        theirs[0].time = time(30);
+        let power = item("Power", 60.0);
+        theirs.insert(
+            0,
+            Change {
+                id: Uuid::new_v4(),
+                time: time(-10),
+                user: "ben".to_owned(),
+                item: power.id,
+                before: None,
+                after: Some(power),
+            },
+        );
+
+        app.merge_history(theirs.clone());
+        assert_eq!(app.history.len(), 2);
+        assert_eq!(app.history[0].user, "ben");
+        assert_eq!(app.history[1].after.as_ref().unwrap().price, 820.0);
+
+        // merging again changes nothing, older versions are ignored
+        let older = app.history.clone();
+        theirs[1].time = time(20);
+        app.merge_history(theirs);
+        assert!(app.history == older);
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+
+use crate::history::Change;
+use crate::model::{FinItem, ItemTemplate};
+use crate::rules::Rule;
+use crate::trash::Trashed;
+
+/// File extension of ledger documents
+pub(crate) const EXTENSION: &str = "fincal";
+
+/// Most entries in the recent files menu
+pub(crate) const MAX_RECENT_FILES: usize = 8;
+
+/// The data of one household, year or trip, saved as a ledger document
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Ledger {
+    pub(crate) items: Vec<FinItem>,
+    pub(crate) categories: Vec<String>,
+    pub(crate) rules: Vec<Rule>,
+    pub(crate) templates: Vec<ItemTemplate>,
+    /// Activity log of the items
+    pub(crate) history: Vec<Change>,
+    /// Deleted items
+    pub(crate) trash: Vec<Trashed>,
+}
+
+/// Error returned when a ledger document can't be read
+#[derive(Debug, PartialEq, Eq)]
+pub struct LedgerError(pub(crate) String);
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Ledger {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, LedgerError> {
+        serde_json::from_slice(bytes).map_err(|e| LedgerError(e.to_string()))
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+}
@@ -2,6 +2,27 @@
 
 mod app;
 mod common;
+mod crypto;
+mod document;
+mod files;
+mod formats;
+mod history;
+mod ledger;
 mod model;
+mod persistence;
+mod recovery;
+mod report;
+mod rules;
+mod shorthand;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+mod store;
+#[cfg(not(target_arch = "wasm32"))]
+mod sync;
+mod trash;
+mod undo;
 mod views;
+mod workspace;
 pub use app::TemplateApp;
+
+/// Names the app and the folder of its saved state
+pub const APP_NAME: &str = "egui-fincal";
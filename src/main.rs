@@ -9,7 +9,7 @@ fn main() {
 
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
-        "egui-fincal",
+        egui_fincal::APP_NAME,
         native_options,
         Box::new(|cc| Box::new(egui_fincal::TemplateApp::new(cc))),
     );
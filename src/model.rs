@@ -3,20 +3,132 @@ use std::{
     fmt::{self},
     str::FromStr,
 };
+use uuid::Uuid;
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
-//#[serde(default)]
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
 pub struct FinItem {
+    /// Stable identity across edits, old state gets new ids
+    #[serde(default = "Uuid::new_v4")]
+    pub(crate) id: Uuid,
     pub(crate) date: NaiveDate,
     pub(crate) item: String,
     pub(crate) category: Option<String>,
     pub(crate) price: f32,
     pub(crate) owner: String,
     pub(crate) ratio: f32,
+    #[serde(default)]
+    pub(crate) memo: Option<String>,
+    /// Bank transaction id (e.g. the OFX FITID), used to skip re-imports
+    #[serde(default)]
+    pub(crate) fitid: Option<String>,
+    #[serde(default)]
+    pub(crate) recurrence: Option<Recurrence>,
+    /// Bank account the item was imported from
+    #[serde(default)]
+    pub(crate) account: Option<String>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
 
     // viewmodel
     #[serde(skip)]
     pub(crate) editable: bool,
+    #[serde(skip)]
+    pub(crate) selected: bool,
+}
+
+// the viewmodel fields don't make items different
+impl PartialEq for FinItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.date == other.date
+            && self.item == other.item
+            && self.category == other.category
+            && self.price == other.price
+            && self.owner == other.owner
+            && self.ratio == other.ratio
+            && self.memo == other.memo
+            && self.fitid == other.fitid
+            && self.recurrence == other.recurrence
+            && self.account == other.account
+            && self.tags == other.tags
+    }
+}
+
+impl FinItem {
+    /// Create a new item with the default category, owner and ratio
+    pub(crate) fn new(date: NaiveDate, item: String, price: f32) -> Self {
+        FinItem {
+            id: Uuid::new_v4(),
+            date,
+            item,
+            category: Some("category".to_string()),
+            price,
+            owner: "MB".to_string(),
+            ratio: 0.5,
+            memo: None,
+            fitid: None,
+            recurrence: None,
+            account: None,
+            tags: Vec::new(),
+            editable: false,
+            selected: false,
+        }
+    }
+}
+
+/// A frequent expense saved as favorite, inserted with one click
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+pub struct ItemTemplate {
+    pub(crate) item: String,
+    pub(crate) category: Option<String>,
+    /// Typical price
+    pub(crate) price: f32,
+    pub(crate) owner: String,
+    pub(crate) ratio: f32,
+}
+
+impl ItemTemplate {
+    pub(crate) fn from_item(item: &FinItem) -> Self {
+        ItemTemplate {
+            item: item.item.clone(),
+            category: item.category.clone(),
+            price: item.price,
+            owner: item.owner.clone(),
+            ratio: item.ratio,
+        }
+    }
+
+    pub(crate) fn to_item(&self, date: NaiveDate) -> FinItem {
+        let mut item = FinItem::new(date, self.item.clone(), self.price);
+        item.category = self.category.clone();
+        item.owner = self.owner.clone();
+        item.ratio = self.ratio;
+        item
+    }
+}
+
+/// How often a recurring bill repeats
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recurrence {
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Recurrence {
+    pub(crate) const ALL: [Recurrence; 3] =
+        [Recurrence::Weekly, Recurrence::Monthly, Recurrence::Yearly];
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Recurrence::Weekly => "Weekly",
+            Recurrence::Monthly => "Monthly",
+            Recurrence::Yearly => "Yearly",
+        };
+        write!(f, "{name}")
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -61,14 +173,21 @@ impl FromStr for FinItem {
         let r_fromstr = splits[5].parse::<f32>().map_err(|_| ParseFinItemError)?;
 
         Ok(FinItem {
+            id: Uuid::new_v4(),
             date: d_fromstr,
             item: i_fromstr,
             category: Some(c_fromstr),
             price: p_fromstr,
             owner: o_fromstr,
             ratio: r_fromstr,
+            memo: None,
+            fitid: None,
+            recurrence: None,
+            account: None,
+            tags: Vec::new(),
             // todo: can this be omitted?
             editable: false,
+            selected: false,
         })
     }
 }
@@ -0,0 +1,393 @@
+use log::{info, warn};
+use serde_json::Value;
+use std::fmt;
+
+use crate::app::TemplateApp;
+use crate::crypto::{self, Cipher};
+
+mod v0;
+
+/// Version of the persisted state. Raise it with every change that the state
+/// of the version before can't be read with, and add a migration for it.
+pub(crate) const SCHEMA_VERSION: u64 = 1;
+
+/// Migrations of the persisted state, the first one from version 1 to 2.
+/// Version 0 is the unversioned RON state, see `from_ron`. Every change of the
+/// state adds a fixture to the tests below.
+const MIGRATIONS: &[Migration] = &[];
+
+type Migration = fn(Value) -> Result<Value, StateError>;
+
+/// Error returned when the persisted state can't be read or migrated
+#[derive(Debug, PartialEq, Eq)]
+pub struct StateError(pub(crate) String);
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The state with its version, or the json of it encrypted with the
+/// passphrase
+#[derive(serde::Serialize)]
+struct Persisted<'a> {
+    version: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<&'a TemplateApp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encrypted: Option<String>,
+}
+
+/// Storage key of the state as it was before migrating or failing to load
+fn backup_key(version: u64) -> String {
+    format!("{}_backup_v{}", eframe::APP_KEY, version)
+}
+
+/// Read the persisted state, migrating it from older versions. If it can't be
+/// read the app starts empty with the error shown, and the state is kept as
+/// backup on the next save. An encrypted state waits to be unlocked.
+pub(crate) fn load(storage: &dyn eframe::Storage) -> TemplateApp {
+    let Some(raw) = storage.get_string(eframe::APP_KEY) else {
+        return TemplateApp::default();
+    };
+    if is_encrypted(&raw) {
+        let mut app = TemplateApp::default();
+        app.locked = Some(raw);
+        return app;
+    }
+
+    let (version, read) = read(&raw, None);
+    match read {
+        Ok(mut app) => {
+            if version < SCHEMA_VERSION {
+                info!("Migrated the state from version {}", version);
+                app.state_backup = Some((backup_key(version), raw));
+            }
+            app
+        }
+        Err(e) => {
+            warn!("Failed to load the state of version {}: {}", version, e);
+            let mut app = TemplateApp::default();
+            app.state_error = Some(StateError(format!(
+                "{e}\n\nThe saved data is kept as \"{}\".",
+                backup_key(version)
+            )));
+            app.state_backup = Some((backup_key(version), raw));
+            app
+        }
+    }
+}
+
+/// Read the persisted state encrypted with the passphrase
+pub(crate) fn unlock(raw: &str, passphrase: &str) -> Result<TemplateApp, StateError> {
+    let (version, encrypted) = split(raw);
+    let Some(Value::String(encrypted)) = encrypted.get("encrypted") else {
+        return Err(StateError("The data is not encrypted".to_owned()));
+    };
+    let (cipher, json) = crypto::unseal_with(passphrase, encrypted)?;
+    let state = serde_json::from_str(&json).map_err(|e| StateError(e.to_string()))?;
+    let mut app = parse(version, state)?;
+    app.cipher = Some(cipher);
+    Ok(app)
+}
+
+/// Read a persisted state of any version, e.g. a backup
+pub(crate) fn from_str(raw: &str, cipher: Option<&Cipher>) -> Result<TemplateApp, StateError> {
+    read(raw, cipher).1
+}
+
+/// Whether the state is encrypted and needs the passphrase to be read
+fn is_encrypted(raw: &str) -> bool {
+    split(raw).1.contains_key("encrypted")
+}
+
+/// The version of the persisted state and the rest of it
+fn split(raw: &str) -> (u64, serde_json::Map<String, Value>) {
+    match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Object(persisted)) => (
+            persisted
+                .get("version")
+                .and_then(Value::as_u64)
+                .unwrap_or(0),
+            persisted,
+        ),
+        // written by eframe before the state had versions
+        _ => {
+            let mut persisted = serde_json::Map::new();
+            persisted.insert("state".to_owned(), Value::String(raw.to_owned()));
+            (0, persisted)
+        }
+    }
+}
+
+/// The version of the persisted state and the state migrated from it
+fn read(raw: &str, cipher: Option<&Cipher>) -> (u64, Result<TemplateApp, StateError>) {
+    let (version, mut persisted) = split(raw);
+    let state = match (persisted.remove("encrypted"), cipher) {
+        (Some(Value::String(encrypted)), Some(cipher)) => cipher
+            .unseal(&encrypted)
+            .map_err(StateError::from)
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| StateError(e.to_string()))),
+        (Some(_), _) => Err(StateError("The data is encrypted".to_owned())),
+        (None, _) => Ok(persisted.remove("state").unwrap_or(Value::Null)),
+    };
+    (version, state.and_then(|state| parse(version, state)))
+}
+
+fn parse(version: u64, state: Value) -> Result<TemplateApp, StateError> {
+    migrate(version, state)
+        .and_then(|s| serde_json::from_value(s).map_err(|e| StateError(e.to_string())))
+}
+
+/// The state with its version, encrypted if there is a cipher
+pub(crate) fn to_string(app: &TemplateApp, cipher: Option<&Cipher>) -> Option<String> {
+    let json = |persisted: &Persisted<'_>| {
+        serde_json::to_string(persisted)
+            .map_err(|e| warn!("Failed to save the state: {}", e))
+            .ok()
+    };
+    let Some(cipher) = cipher else {
+        return json(&Persisted {
+            version: SCHEMA_VERSION,
+            state: Some(app),
+            encrypted: None,
+        });
+    };
+
+    let state = serde_json::to_string(app)
+        .map_err(|e| warn!("Failed to save the state: {}", e))
+        .ok()?;
+    match cipher.seal(&state) {
+        Ok(encrypted) => json(&Persisted {
+            version: SCHEMA_VERSION,
+            state: None,
+            encrypted: Some(encrypted),
+        }),
+        Err(e) => {
+            warn!("Failed to encrypt the state: {}", e);
+            None
+        }
+    }
+}
+
+/// Write the state with its version, after the backup of the state it was
+/// loaded from
+pub(crate) fn save(storage: &mut dyn eframe::Storage, app: &mut TemplateApp) {
+    if let Some((key, raw)) = app.state_backup.take() {
+        match seal_backup(raw, app.cipher.as_ref()) {
+            Ok(raw) => {
+                storage.set_string(&key, raw);
+                if !app.state_backups.contains(&key) {
+                    app.state_backups.push(key);
+                }
+            }
+            Err(e) => warn!("Failed to keep the state as {}: {}", key, e),
+        }
+    }
+    if let Some(raw) = to_string(app, app.cipher.as_ref()) {
+        storage.set_string(eframe::APP_KEY, raw);
+    }
+}
+
+/// Encrypt the backups of the state with the new cipher, after decrypting
+/// them with the old one
+pub(crate) fn reseal_backups(
+    storage: &mut dyn eframe::Storage,
+    keys: &[String],
+    old: Option<&Cipher>,
+    new: Option<&Cipher>,
+) {
+    for key in keys {
+        let Some(raw) = storage.get_string(key) else {
+            continue;
+        };
+        match open_backup(raw, old).and_then(|raw| seal_backup(raw, new)) {
+            Ok(raw) => storage.set_string(key, raw),
+            Err(e) => warn!("Failed to encrypt the backup {}: {}", key, e),
+        }
+    }
+}
+
+/// A backup of the state encrypted with the cipher, as it is without one.
+/// The state is kept as read, it is migrated when the backup is restored.
+fn seal_backup(raw: String, cipher: Option<&Cipher>) -> Result<String, StateError> {
+    let Some(cipher) = cipher.filter(|_| !is_encrypted(&raw)) else {
+        return Ok(raw);
+    };
+    let (version, mut persisted) = split(&raw);
+    let state = persisted.remove("state").unwrap_or(Value::Null);
+    let json = serde_json::to_string(&state).map_err(|e| StateError(e.to_string()))?;
+    let persisted = serde_json::json!({ "version": version, "encrypted": cipher.seal(&json)? });
+    Ok(persisted.to_string())
+}
+
+/// A backup of the state decrypted with the cipher
+fn open_backup(raw: String, cipher: Option<&Cipher>) -> Result<String, StateError> {
+    if !is_encrypted(&raw) {
+        return Ok(raw);
+    }
+    let (version, persisted) = split(&raw);
+    let (Some(Value::String(encrypted)), Some(cipher)) = (persisted.get("encrypted"), cipher)
+    else {
+        return Err(StateError("The data is encrypted".to_owned()));
+    };
+    let state: Value =
+        serde_json::from_str(&cipher.unseal(encrypted)?).map_err(|e| StateError(e.to_string()))?;
+    Ok(serde_json::json!({ "version": version, "state": state }).to_string())
+}
+
+fn migrate(version: u64, mut state: Value) -> Result<Value, StateError> {
+    if version > SCHEMA_VERSION {
+        return Err(StateError(format!(
+            "The data was saved by a newer version of the app (version {version})"
+        )));
+    }
+    if version == 0 {
+        return from_ron(state);
+    }
+    for migration in MIGRATIONS.iter().skip(version.saturating_sub(1) as usize) {
+        state = migration(state)?;
+    }
+    Ok(state)
+}
+
+/// Version 0: the RON written by eframe. It can't be read without the types,
+/// so it's read with the types of then and written as version 1.
+fn from_ron(state: Value) -> Result<Value, StateError> {
+    let Value::String(ron) = state else {
+        return Err(StateError("The data is not RON".to_owned()));
+    };
+    let state: v0::State = ron::from_str(&ron).map_err(|e| StateError(e.to_string()))?;
+    let state = serde_json::to_value(state).map_err(|e| StateError(e.to_string()))?;
+    migrate(1, state)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use eframe::Storage;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::model::Recurrence;
+    use crate::rules::MatchKind;
+
+    /// The storage of eframe, in memory
+    #[derive(Default)]
+    pub(crate) struct MemoryStorage(pub(crate) HashMap<String, String>);
+
+    impl eframe::Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_owned(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    /// Written by the first version of the app
+    const BASELINE_RON: &str = r#"(items:[(date:"2023-01-05",item:"Rent",category:Some("a"),price:500.0,owner:"MB",ratio:0.5)],categories:["a","b","c"])"#;
+
+    /// Written before the state had versions, with every field of then
+    const UNVERSIONED_RON: &str = r#"(
+        items: [(id: "5b0c3f3e-8a1e-4a52-9d4a-0d2f6b1c7e11", date: "2023-02-01", item: "Power",
+            category: Some("bills"), price: 60.5, owner: "MB", ratio: 0.5, memo: Some("meter"),
+            fitid: Some("T1"), recurrence: Some(Monthly), account: Some("DE01"), tags: ["home"])],
+        categories: ["bills"],
+        credits_as_income: true,
+        rules: [(name: "Power", enabled: true, pattern: "power", match_kind: Regex,
+            min_amount: None, max_amount: Some(100.0), account: "", category: Some("bills"),
+            owner: None, ratio: None, tags: "")],
+        templates: [(item: "Coffee", category: None, price: 3.2, owner: "MB", ratio: 1.0)],
+        document: Some("/home/mb/household.fincal"),
+        recent_files: ["/home/mb/household.fincal"],
+        workspace: "Household",
+        workspaces: [(name: "Trip", ledger: (items: [], categories: ["travel"], rules: [],
+            templates: []), credits_as_income: false, document: None)],
+    )"#;
+
+    /// Written by version 1
+    const VERSION_1_JSON: &str = r#"{"version":1,"state":{
+        "items":[{"id":"5b0c3f3e-8a1e-4a52-9d4a-0d2f6b1c7e11","date":"2023-02-01","item":"Power",
+            "category":"bills","price":60.5,"owner":"MB","ratio":0.5,"memo":null,"fitid":null,
+            "recurrence":"Yearly","account":null,"tags":[]}],
+        "categories":["bills"],"credits_as_income":false,"rules":[],"templates":[],
+        "document":null,"recent_files":[],"workspace":"Trip","workspaces":[]}}"#;
+
+    fn load_str(raw: &str) -> TemplateApp {
+        let mut storage = MemoryStorage::default();
+        storage.0.insert(eframe::APP_KEY.to_owned(), raw.to_owned());
+        load(&storage)
+    }
+
+    #[test]
+    fn reads_the_baseline_state() {
+        let app = load_str(BASELINE_RON);
+        assert!(app.state_error.is_none());
+        assert_eq!(app.items.len(), 1);
+        assert_eq!(app.items[0].item, "Rent");
+        assert_eq!(app.items[0].price, 500.0);
+        assert_eq!(app.categories, ["a", "b", "c"]);
+        assert_eq!(app.workspace, "Household");
+        // the state as it was is kept
+        let (key, raw) = app.state_backup.unwrap();
+        assert_eq!(key, backup_key(0));
+        assert_eq!(raw, BASELINE_RON);
+    }
+
+    #[test]
+    fn reads_the_unversioned_state() {
+        let app = load_str(UNVERSIONED_RON);
+        assert!(app.state_error.is_none());
+        let item = &app.items[0];
+        assert_eq!(item.id.to_string(), "5b0c3f3e-8a1e-4a52-9d4a-0d2f6b1c7e11");
+        assert_eq!(item.recurrence, Some(Recurrence::Monthly));
+        assert_eq!(item.tags, ["home"]);
+        assert!(app.credits_as_income);
+        assert_eq!(app.rules[0].match_kind, MatchKind::Regex);
+        assert_eq!(app.rules[0].max_amount, Some(100.0));
+        assert_eq!(app.templates[0].item, "Coffee");
+        assert_eq!(app.recent_files.len(), 1);
+        assert_eq!(app.workspaces[0].name, "Trip");
+        assert_eq!(app.workspaces[0].ledger.categories, ["travel"]);
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let app = load_str(VERSION_1_JSON);
+        assert!(app.state_error.is_none());
+        assert!(app.state_backup.is_none());
+        assert_eq!(app.items[0].recurrence, Some(Recurrence::Yearly));
+        assert_eq!(app.workspace, "Trip");
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let mut app = load_str(UNVERSIONED_RON);
+        let mut storage = MemoryStorage::default();
+        save(&mut storage, &mut app);
+        assert_eq!(storage.get_string(&backup_key(0)).unwrap(), UNVERSIONED_RON);
+
+        let saved = storage.get_string(eframe::APP_KEY).unwrap();
+        assert!(saved.starts_with(&format!("{{\"version\":{SCHEMA_VERSION}")));
+        let reloaded = load(&storage);
+        assert!(reloaded.state_backup.is_none());
+        assert!(reloaded.items == app.items);
+        assert!(reloaded.rules == app.rules);
+    }
+
+    #[test]
+    fn keeps_a_state_it_cant_read() {
+        let app = load_str("{\"version\":99,\"state\":{}}");
+        assert!(app.state_error.unwrap().0.contains("newer version"));
+        assert_eq!(app.state_backup.unwrap().0, backup_key(99));
+
+        let app = load_str("(items: [(date: \"not a date\")])");
+        assert!(app.state_error.is_some());
+        assert!(app.items.is_empty());
+        assert_eq!(app.state_backup.unwrap().0, backup_key(0));
+    }
+}
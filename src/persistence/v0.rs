@@ -0,0 +1,111 @@
+//! The state as eframe saved it before the state had versions, in RON. The
+//! types are kept as they were then, so the current ones are free to change:
+//! the state read with them is written as version 1 and migrated from there.
+
+use chrono::NaiveDate;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn default_workspace() -> String {
+    "Household".to_owned()
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(super) struct State {
+    #[serde(default)]
+    items: Vec<Item>,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    credits_as_income: bool,
+    #[serde(default)]
+    rules: Vec<Rule>,
+    #[serde(default)]
+    templates: Vec<Template>,
+    #[serde(default)]
+    document: Option<PathBuf>,
+    #[serde(default)]
+    recent_files: Vec<PathBuf>,
+    #[serde(default = "default_workspace")]
+    workspace: String,
+    #[serde(default)]
+    workspaces: Vec<Workspace>,
+}
+
+/// Items of the first versions have no id, they get one when read
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Item {
+    #[serde(default = "Uuid::new_v4")]
+    id: Uuid,
+    date: NaiveDate,
+    item: String,
+    category: Option<String>,
+    price: f32,
+    owner: String,
+    ratio: f32,
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    fitid: Option<String>,
+    #[serde(default)]
+    recurrence: Option<Recurrence>,
+    #[serde(default)]
+    account: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+enum Recurrence {
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Rule {
+    name: String,
+    enabled: bool,
+    pattern: String,
+    match_kind: MatchKind,
+    min_amount: Option<f32>,
+    max_amount: Option<f32>,
+    account: String,
+    category: Option<String>,
+    owner: Option<String>,
+    ratio: Option<f32>,
+    tags: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+enum MatchKind {
+    Contains,
+    Regex,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Template {
+    item: String,
+    category: Option<String>,
+    price: f32,
+    owner: String,
+    ratio: f32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+struct Workspace {
+    name: String,
+    ledger: Ledger,
+    credits_as_income: bool,
+    document: Option<PathBuf>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+struct Ledger {
+    items: Vec<Item>,
+    categories: Vec<String>,
+    rules: Vec<Rule>,
+    templates: Vec<Template>,
+}
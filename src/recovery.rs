@@ -0,0 +1,519 @@
+use chrono::{Datelike, NaiveDateTime};
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+use uuid::Uuid;
+
+use crate::app::TemplateApp;
+use crate::history::Change;
+use crate::ledger::Ledger;
+use crate::model::{FinItem, ItemTemplate};
+use crate::persistence;
+use crate::rules::Rule;
+use crate::trash::Trashed;
+
+/// Storage key of the changes since the state was last saved, if there is
+/// no journal file
+const JOURNAL_KEY: &str = "journal";
+
+/// Name of the journal file, next to the state
+#[cfg(not(target_arch = "wasm32"))]
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// Entries after which the state is saved, so the journal stays short
+const MAX_JOURNAL_ENTRIES: usize = 500;
+
+/// Storage key of the list of backups
+const BACKUPS_KEY: &str = "backups";
+
+/// Seconds between checks for changes to write to the journal
+const JOURNAL_INTERVAL: f64 = 0.5;
+
+/// Changes of a ledger, written to the journal right after they are made.
+/// Replaying them again gives the same ledger.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct JournalEntry {
+    workspace: String,
+    /// Added or edited items
+    changed: Vec<FinItem>,
+    removed: Vec<Uuid>,
+    categories: Option<Vec<String>>,
+    rules: Option<Vec<Rule>>,
+    templates: Option<Vec<ItemTemplate>>,
+    trash: Option<Vec<Trashed>>,
+    /// Added or coalesced changes of the activity log
+    #[serde(default)]
+    history: Vec<Change>,
+}
+
+impl JournalEntry {
+    /// The changes from the old to the new ledger, None if there are none
+    fn diff(workspace: &str, old: &Ledger, new: &Ledger) -> Option<Self> {
+        let old_items: HashMap<Uuid, &FinItem> = old.items.iter().map(|i| (i.id, i)).collect();
+        let new_ids: HashSet<Uuid> = new.items.iter().map(|i| i.id).collect();
+        let old_history: HashMap<Uuid, &Change> = old.history.iter().map(|c| (c.id, c)).collect();
+        let entry = JournalEntry {
+            workspace: workspace.to_owned(),
+            changed: new
+                .items
+                .iter()
+                .filter(|i| old_items.get(&i.id) != Some(i))
+                .cloned()
+                .collect(),
+            removed: old
+                .items
+                .iter()
+                .map(|i| i.id)
+                .filter(|id| !new_ids.contains(id))
+                .collect(),
+            categories: (old.categories != new.categories).then(|| new.categories.clone()),
+            rules: (old.rules != new.rules).then(|| new.rules.clone()),
+            templates: (old.templates != new.templates).then(|| new.templates.clone()),
+            trash: (old.trash != new.trash).then(|| new.trash.clone()),
+            history: new
+                .history
+                .iter()
+                .filter(|c| old_history.get(&c.id) != Some(c))
+                .cloned()
+                .collect(),
+        };
+        let empty = entry.changed.is_empty()
+            && entry.removed.is_empty()
+            && entry.categories.is_none()
+            && entry.rules.is_none()
+            && entry.templates.is_none()
+            && entry.trash.is_none()
+            && entry.history.is_empty();
+        (!empty).then_some(entry)
+    }
+
+    fn apply(self, ledger: &mut Ledger) {
+        ledger.items.retain(|i| !self.removed.contains(&i.id));
+        for item in self.changed {
+            match ledger.items.iter_mut().find(|i| i.id == item.id) {
+                Some(old) => *old = item,
+                None => ledger.items.push(item),
+            }
+        }
+        if let Some(categories) = self.categories {
+            ledger.categories = categories;
+        }
+        if let Some(rules) = self.rules {
+            ledger.rules = rules;
+        }
+        if let Some(templates) = self.templates {
+            ledger.templates = templates;
+        }
+        if let Some(trash) = self.trash {
+            ledger.trash = trash;
+        }
+        for change in self.history {
+            match ledger.history.iter_mut().find(|c| c.id == change.id) {
+                Some(old) => *old = change,
+                None => ledger.history.push(change),
+            }
+        }
+    }
+}
+
+/// How long a backup is kept
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BackupKind {
+    Daily,
+    Weekly,
+    /// The state replaced by the last restore
+    BeforeRestore,
+}
+
+impl BackupKind {
+    pub(crate) const ALL: [BackupKind; 3] = [
+        BackupKind::Daily,
+        BackupKind::Weekly,
+        BackupKind::BeforeRestore,
+    ];
+
+    /// Number of backups kept, the oldest one is overwritten
+    fn slots(&self) -> usize {
+        match self {
+            BackupKind::Daily => 7,
+            BackupKind::Weekly => 4,
+            BackupKind::BeforeRestore => 1,
+        }
+    }
+}
+
+impl fmt::Display for BackupKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupKind::Daily => write!(f, "Daily"),
+            BackupKind::Weekly => write!(f, "Weekly"),
+            BackupKind::BeforeRestore => write!(f, "Before restore"),
+        }
+    }
+}
+
+/// A copy of the persisted state in one of the rotating slots
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+pub(crate) struct Backup {
+    pub(crate) kind: BackupKind,
+    slot: usize,
+    pub(crate) time: NaiveDateTime,
+    /// Items in all ledgers
+    pub(crate) items: usize,
+}
+
+impl Backup {
+    pub(crate) fn key(&self) -> String {
+        format!("backup_{:?}_{}", self.kind, self.slot).to_lowercase()
+    }
+}
+
+/// The backups in the storage, newest first
+pub(crate) fn backups(storage: &dyn eframe::Storage) -> Vec<Backup> {
+    storage
+        .get_string(BACKUPS_KEY)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// The journal file next to the state of the app. Appending to it leaves
+/// the state and the backups alone, which the storage rewrites at once on
+/// every flush.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn journal_path(app_name: &str) -> Option<std::path::PathBuf> {
+    directories_next::ProjectDirs::from("", "", app_name)
+        .map(|dirs| dirs.data_dir().join(JOURNAL_FILE))
+}
+
+fn now() -> NaiveDateTime {
+    chrono::offset::Local::now().naive_local()
+}
+
+impl TemplateApp {
+    /// Replay the changes that were not saved before the app last exited
+    pub(crate) fn replay_journal(&mut self, storage: &dyn eframe::Storage) {
+        let entries: Vec<JournalEntry> = match self.read_journal(storage) {
+            journal if !journal.is_empty() => journal
+                .lines()
+                .filter_map(|line| match &self.cipher {
+                    // sealed lines, or json written before the passphrase was set
+                    Some(cipher) if !line.starts_with('{') => cipher.unseal(line).ok(),
+                    _ => Some(line.to_owned()),
+                })
+                .filter_map(|json| serde_json::from_str(&json).ok())
+                .collect(),
+            _ => return,
+        };
+        info!("Recovering {} changes after an unclean exit", entries.len());
+        self.journal_entries = entries.len();
+
+        for entry in entries {
+            if entry.workspace == self.workspace {
+                let mut ledger = self.ledger();
+                entry.apply(&mut ledger);
+                self.set_ledger(ledger);
+            } else if let Some(workspace) = self
+                .workspaces
+                .iter_mut()
+                .find(|w| w.name == entry.workspace)
+            {
+                entry.apply(&mut workspace.ledger);
+            }
+        }
+    }
+
+    /// Append the changes of the shown ledger to the journal right away
+    pub(crate) fn write_journal(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let time = ctx.input().time;
+        if time - self.journal_time < JOURNAL_INTERVAL {
+            return;
+        }
+        self.journal_time = time;
+
+        let entry = match &self.journaled {
+            // another ledger was shown, the state was saved when switching
+            Some((workspace, journaled)) if *workspace == self.workspace => {
+                let unchanged = self.items == journaled.items
+                    && self.categories == journaled.categories
+                    && self.rules == journaled.rules
+                    && self.templates == journaled.templates
+                    && self.trash == journaled.trash
+                    && self.history == journaled.history;
+                if unchanged {
+                    return;
+                }
+                JournalEntry::diff(workspace, journaled, &self.ledger())
+            }
+            _ => None,
+        };
+        self.journaled = Some((self.workspace.clone(), self.ledger()));
+
+        let (Some(entry), Some(storage)) = (entry, frame.storage_mut()) else {
+            return;
+        };
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|json| match &self.cipher {
+                Some(cipher) => cipher.seal(&json).map_err(|e| e.to_string()),
+                None => Ok(json),
+            });
+        match line.and_then(|line| {
+            self.append_journal(storage, &line)
+                .map_err(|e| e.to_string())
+        }) {
+            Ok(()) => {
+                self.journal_entries += 1;
+                if self.journal_entries >= MAX_JOURNAL_ENTRIES {
+                    self.save_state = true;
+                }
+            }
+            Err(e) => warn!("Failed to write the journal: {}", e),
+        }
+    }
+
+    /// The journal file, and the journal in the storage if there is no file
+    /// or it was written by an older version
+    fn read_journal(&self, storage: &dyn eframe::Storage) -> String {
+        let mut journal = storage.get_string(JOURNAL_KEY).unwrap_or_default();
+        if let Some(path) = &self.journal_path {
+            match std::fs::read_to_string(path) {
+                Ok(lines) => journal.push_str(&lines),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to read {}: {}", path.display(), e),
+            }
+        }
+        journal
+    }
+
+    fn append_journal(&self, storage: &mut dyn eframe::Storage, line: &str) -> io::Result<()> {
+        let Some(path) = &self.journal_path else {
+            // the browser writes the storage right away
+            let mut journal = storage.get_string(JOURNAL_KEY).unwrap_or_default();
+            journal.push_str(line);
+            journal.push('\n');
+            storage.set_string(JOURNAL_KEY, journal);
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Everything in the journal is in the saved state
+    pub(crate) fn clear_journal(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string(JOURNAL_KEY, String::new());
+        if let Some(path) = &self.journal_path {
+            match std::fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to clear {}: {}", path.display(), e),
+            }
+        }
+        self.journal_entries = 0;
+    }
+
+    /// Copy the saved state into the daily and weekly backups, once a day
+    /// and once a week
+    pub(crate) fn rotate_backups(&mut self, storage: &mut dyn eframe::Storage) {
+        let now = now();
+        let daily = !self
+            .backups
+            .iter()
+            .any(|b| b.kind == BackupKind::Daily && b.time.date() == now.date());
+        let weekly = !self
+            .backups
+            .iter()
+            .any(|b| b.kind == BackupKind::Weekly && b.time.iso_week() == now.iso_week());
+
+        if daily {
+            self.backup(storage, BackupKind::Daily, now);
+        }
+        if weekly {
+            self.backup(storage, BackupKind::Weekly, now);
+        }
+    }
+
+    /// Copy the saved state into the next slot of the kind
+    fn backup(&mut self, storage: &mut dyn eframe::Storage, kind: BackupKind, time: NaiveDateTime) {
+        let Some(state) = storage.get_string(eframe::APP_KEY) else {
+            return;
+        };
+        let mut of_kind: Vec<&Backup> = self.backups.iter().filter(|b| b.kind == kind).collect();
+        let slot = if of_kind.len() < kind.slots() {
+            (0..kind.slots())
+                .find(|s| !of_kind.iter().any(|b| b.slot == *s))
+                .unwrap_or_default()
+        } else {
+            of_kind.sort_by_key(|b| b.time);
+            of_kind[0].slot
+        };
+        self.backups.retain(|b| b.kind != kind || b.slot != slot);
+
+        let backup = Backup {
+            kind,
+            slot,
+            time,
+            items: self.items.len()
+                + self
+                    .workspaces
+                    .iter()
+                    .map(|w| w.ledger.items.len())
+                    .sum::<usize>(),
+        };
+        storage.set_string(&backup.key(), state);
+        self.backups.insert(0, backup);
+        self.backups.sort_by_key(|b| std::cmp::Reverse(b.time));
+        match serde_json::to_string(&self.backups) {
+            Ok(backups) => storage.set_string(BACKUPS_KEY, backups),
+            Err(e) => warn!("Failed to write the backups: {}", e),
+        }
+    }
+
+    /// Replace the state with the backup. The replaced state is kept as
+    /// backup itself.
+    pub(crate) fn restore(&mut self, storage: &mut dyn eframe::Storage, backup: &Backup) {
+        let Some(raw) = storage.get_string(&backup.key()) else {
+            warn!("The backup of {} is missing", backup.time);
+            return;
+        };
+        let restored = match persistence::from_str(&raw, self.cipher.as_ref()) {
+            Ok(restored) => restored,
+            Err(e) => {
+                self.state_error = Some(e);
+                return;
+            }
+        };
+
+        persistence::save(storage, self);
+        self.backup(storage, BackupKind::BeforeRestore, now());
+
+        let backups = std::mem::take(&mut self.backups);
+        let cipher = self.cipher.take();
+        let journal_path = self.journal_path.take();
+        let state_backups = std::mem::take(&mut self.state_backups);
+        *self = restored;
+        self.backups = backups;
+        self.state_backups = state_backups;
+        self.cipher = cipher;
+        self.journal_path = journal_path;
+        self.reopen_document();
+        self.journaled = None;
+        persistence::save(storage, self);
+        self.clear_journal(storage);
+        storage.flush();
+        info!("Restored the {} backup of {}", backup.kind, backup.time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::persistence::tests::MemoryStorage;
+
+    fn item(name: &str, price: f32) -> FinItem {
+        FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        )
+    }
+
+    fn ledger() -> Ledger {
+        Ledger {
+            items: vec![
+                item("Rent", 500.0),
+                item("Power", 60.0),
+                item("Coffee", 3.0),
+            ],
+            categories: vec!["bills".to_owned()],
+            ..Ledger::default()
+        }
+    }
+
+    #[test]
+    fn diff_applied_gives_the_new_ledger() {
+        let old = ledger();
+        let mut new = old.clone();
+        new.items[0].price = 520.0;
+        new.items.remove(1);
+        new.items.push(item("Cinema", 12.0));
+        new.categories.push("fun".to_owned());
+
+        let entry = JournalEntry::diff("Household", &old, &new).unwrap();
+        assert_eq!(entry.changed.len(), 2);
+        assert_eq!(entry.removed, [old.items[1].id]);
+        assert!(entry.rules.is_none());
+
+        let mut replayed = old.clone();
+        entry.apply(&mut replayed);
+        assert!(replayed == new);
+    }
+
+    #[test]
+    fn no_diff_without_changes() {
+        let mut ledger = ledger();
+        assert!(JournalEntry::diff("Household", &ledger, &ledger.clone()).is_none());
+        // only the viewmodel
+        let old = ledger.clone();
+        ledger.items[0].selected = true;
+        assert!(JournalEntry::diff("Household", &old, &ledger).is_none());
+    }
+
+    #[test]
+    fn replays_the_journal_file() {
+        let path = std::env::temp_dir().join(format!("journal-{}.jsonl", Uuid::new_v4()));
+        let mut storage = MemoryStorage::default();
+        let mut app = TemplateApp::default();
+        app.journal_path = Some(path.clone());
+        let base = ledger();
+        app.set_ledger(base.clone());
+
+        // two changes, as written while editing
+        let mut edited = base.clone();
+        edited.items[0].price = 520.0;
+        let mut deleted = edited.clone();
+        deleted.items.remove(2);
+        for (old, new) in [(&base, &edited), (&edited, &deleted)] {
+            let entry = JournalEntry::diff("Household", old, new).unwrap();
+            let line = serde_json::to_string(&entry).unwrap();
+            app.append_journal(&mut storage, &line).unwrap();
+        }
+        assert!(!storage.0.contains_key(JOURNAL_KEY));
+
+        let mut recovered = TemplateApp::default();
+        recovered.journal_path = Some(path.clone());
+        recovered.set_ledger(base);
+        recovered.replay_journal(&storage);
+        assert!(recovered.items == deleted.items);
+        assert_eq!(recovered.journal_entries, 2);
+
+        recovered.clear_journal(&mut storage);
+        assert!(!path.exists());
+        assert_eq!(recovered.journal_entries, 0);
+    }
+
+    #[test]
+    fn replays_the_journal_in_the_storage() {
+        let mut storage = MemoryStorage::default();
+        let app = TemplateApp::default();
+        let base = ledger();
+        let mut edited = base.clone();
+        edited.items[1].item = "Gas".to_owned();
+        let entry = JournalEntry::diff("Household", &base, &edited).unwrap();
+        app.append_journal(&mut storage, &serde_json::to_string(&entry).unwrap())
+            .unwrap();
+
+        let mut recovered = TemplateApp::default();
+        recovered.set_ledger(base);
+        recovered.replay_journal(&storage);
+        assert_eq!(recovered.items[1].item, "Gas");
+    }
+}
@@ -0,0 +1,341 @@
+use chrono::Datelike;
+use itertools::Itertools;
+use std::collections::HashMap;
+
+use crate::common::{escape_xml, paid_by_owner, spent_by_category, to_name};
+use crate::model::FinItem;
+
+pub(crate) mod chart;
+mod pdf;
+
+use pdf::{Pdf, PAGE_HEIGHT};
+
+/// Chart size in the report, in points
+const CHART_WIDTH: f32 = 515.0;
+const CHART_HEIGHT: f32 = 220.0;
+/// Chart size as a standalone image, in points
+const IMAGE_WIDTH: f32 = 640.0;
+const IMAGE_HEIGHT: f32 = 320.0;
+
+/// The monthly summary shown in the app, ready to be printed
+pub(crate) struct Report {
+    title: String,
+    items: Vec<FinItem>,
+    total: f32,
+    paid_dict: HashMap<String, (f32, f32)>,
+    cat_dict: HashMap<String, f32>,
+}
+
+impl Report {
+    pub(crate) fn new(items: &[FinItem], year: i32, month: u32) -> Self {
+        let items: Vec<FinItem> = items
+            .iter()
+            .filter(|i| i.date.year() == year && i.date.month() == month)
+            .sorted_by_key(|i| i.date)
+            .cloned()
+            .collect();
+
+        Report {
+            title: format!("{} {}", to_name(month), year),
+            total: items.iter().map(|i| i.price).sum(),
+            paid_dict: paid_by_owner(&items),
+            cat_dict: spent_by_category(&items),
+            items,
+        }
+    }
+
+    /// Who owes whom, if the month is shared by two people
+    fn settlement(&self) -> Option<String> {
+        let people: Vec<&String> = self.paid_dict.keys().sorted().collect();
+        let [a, b] = people[..] else {
+            return None;
+        };
+        let owed = self.paid_dict[a].1 - self.paid_dict[b].1;
+        Some(if owed >= 0.0 {
+            format!("{b} owes {a} {owed:.2}")
+        } else {
+            format!("{a} owes {b} {:.2}", -owed)
+        })
+    }
+
+    fn chart(&self) -> chart::Chart {
+        chart::month_chart(&self.title, &self.items, CHART_WIDTH, CHART_HEIGHT)
+    }
+
+    /// The chart alone, sized to be shared as an image
+    pub(crate) fn chart_image(&self) -> chart::Chart {
+        chart::month_chart(&self.title, &self.items, IMAGE_WIDTH, IMAGE_HEIGHT)
+    }
+
+    /// A self-contained html page
+    pub(crate) fn html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>\nbody {{ font-family: sans-serif; margin: 2em; }}\n\
+             table {{ border-collapse: collapse; margin-bottom: 1.5em; }}\n\
+             th, td {{ border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }}\n\
+             td.num {{ text-align: right; }}\n\
+             @media print {{ body {{ margin: 0; }} }}\n</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n",
+            title = escape_xml(&self.title)
+        );
+
+        html.push_str("<h2>Items</h2>\n<table>\n<tr><th>Date</th><th>Item</th><th>Category</th><th>Price</th><th>Name</th><th>Ratio</th><th>Total</th></tr>\n");
+        for item in &self.items {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{:.2}</td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{:.2}</td></tr>\n",
+                item.date,
+                escape_xml(&item.item),
+                escape_xml(item.category.as_deref().unwrap_or("None")),
+                item.price,
+                escape_xml(&item.owner),
+                item.ratio,
+                item.price * item.ratio
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str(
+            "<h2>Paid this month</h2>\n<table>\n<tr><th>Name</th><th>Paid</th><th>Owed</th></tr>\n",
+        );
+        for key in self.paid_dict.keys().sorted() {
+            let (paid, owed) = self.paid_dict[key];
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"num\">{paid:.2}</td><td class=\"num\">{owed:.2}</td></tr>\n",
+                escape_xml(key)
+            ));
+        }
+        html.push_str("</table>\n");
+        html.push_str(&format!("<p>Total spent: {:.2}</p>\n", self.total));
+        if let Some(settlement) = self.settlement() {
+            html.push_str(&format!(
+                "<p><strong>{}</strong></p>\n",
+                escape_xml(&settlement)
+            ));
+        }
+
+        html.push_str(
+            "<h2>Spent by category</h2>\n<table>\n<tr><th>Category</th><th>Paid</th></tr>\n",
+        );
+        for key in self.cat_dict.keys().sorted() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"num\">{:.2}</td></tr>\n",
+                escape_xml(key),
+                self.cat_dict[key]
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Chart</h2>\n");
+        html.push_str(&self.chart().svg());
+        html.push_str("\n</body>\n</html>\n");
+        html
+    }
+
+    /// An A4 pdf document
+    pub(crate) fn pdf(&self) -> Vec<u8> {
+        let mut writer = PdfWriter {
+            pdf: Pdf::new(),
+            y: MARGIN,
+        };
+
+        writer.line(&[&self.title], &[MARGIN], 18.0, true);
+        writer.y += 6.0;
+
+        writer.line(&["Items"], &[MARGIN], 13.0, true);
+        let columns = [MARGIN, 105.0, 255.0, 345.0, 400.0, 470.0, 510.0];
+        writer.line(
+            &[
+                "Date", "Item", "Category", "Price", "Name", "Ratio", "Total",
+            ],
+            &columns,
+            9.0,
+            true,
+        );
+        for item in &self.items {
+            writer.line(
+                &[
+                    &item.date.to_string(),
+                    &truncate(&item.item, 30),
+                    &truncate(item.category.as_deref().unwrap_or("None"), 18),
+                    &format!("{:.2}", item.price),
+                    &truncate(&item.owner, 14),
+                    &item.ratio.to_string(),
+                    &format!("{:.2}", item.price * item.ratio),
+                ],
+                &columns,
+                9.0,
+                false,
+            );
+        }
+        writer.y += 10.0;
+
+        let columns = [MARGIN, 200.0, 280.0];
+        writer.line(&["Paid this month"], &[MARGIN], 13.0, true);
+        writer.line(&["Name", "Paid", "Owed"], &columns, 9.0, true);
+        for key in self.paid_dict.keys().sorted() {
+            let (paid, owed) = self.paid_dict[key];
+            writer.line(
+                &[key, &format!("{paid:.2}"), &format!("{owed:.2}")],
+                &columns,
+                9.0,
+                false,
+            );
+        }
+        writer.line(
+            &[&format!("Total spent: {:.2}", self.total)],
+            &[MARGIN],
+            10.0,
+            false,
+        );
+        if let Some(settlement) = self.settlement() {
+            writer.line(&[&settlement], &[MARGIN], 10.0, true);
+        }
+        writer.y += 10.0;
+
+        let columns = [MARGIN, 200.0];
+        writer.line(&["Spent by category"], &[MARGIN], 13.0, true);
+        writer.line(&["Category", "Paid"], &columns, 9.0, true);
+        for key in self.cat_dict.keys().sorted() {
+            writer.line(
+                &[key, &format!("{:.2}", self.cat_dict[key])],
+                &columns,
+                9.0,
+                false,
+            );
+        }
+        writer.y += 10.0;
+
+        writer.reserve(CHART_HEIGHT);
+        writer.pdf.chart(&self.chart(), MARGIN, writer.y);
+
+        writer.pdf.finish()
+    }
+}
+
+const MARGIN: f32 = 40.0;
+
+/// Lays out lines of text top to bottom, starting new pages as needed
+struct PdfWriter {
+    pdf: Pdf,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn reserve(&mut self, height: f32) {
+        if self.y + height > PAGE_HEIGHT - MARGIN {
+            self.pdf.new_page();
+            self.y = MARGIN;
+        }
+    }
+
+    /// Write one line with a cell per column position
+    fn line(&mut self, cells: &[&str], columns: &[f32], size: f32, bold: bool) {
+        let height = size * 1.5;
+        self.reserve(height);
+        self.y += height;
+        for (cell, x) in cells.iter().zip(columns) {
+            self.pdf.text(*x, self.y, size, bold, cell);
+        }
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() > max {
+        format!("{}…", s.chars().take(max - 1).collect::<String>())
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn item(name: &str, price: f32, owner: &str, month: u32) -> FinItem {
+        let mut item = FinItem::new(
+            NaiveDate::from_ymd_opt(2023, month, 1).unwrap(),
+            name.to_owned(),
+            price,
+        );
+        item.owner = owner.to_owned();
+        item
+    }
+
+    fn report() -> Report {
+        let mut rent = item("Rent & more", 800.0, "MB", 5);
+        rent.category = Some("bills".to_owned());
+        let items = [
+            item("Power", 60.0, "anna", 5),
+            item("Train", 30.0, "anna", 6),
+            rent,
+        ];
+        Report::new(&items, 2023, 5)
+    }
+
+    #[test]
+    fn totals_the_month() {
+        let report = report();
+        assert_eq!(report.title, "May 2023");
+        assert_eq!(report.items.len(), 2);
+        assert_eq!(report.total, 860.0);
+        assert_eq!(report.paid_dict["MB"], (800.0, 400.0));
+        assert_eq!(report.paid_dict["anna"], (60.0, 30.0));
+        assert_eq!(report.cat_dict["bills"], 800.0);
+        assert_eq!(report.cat_dict["category"], 60.0);
+        assert_eq!(report.settlement().as_deref(), Some("anna owes MB 370.00"));
+    }
+
+    #[test]
+    fn settles_only_between_two_people() {
+        let items = [
+            item("Power", 60.0, "anna", 5),
+            item("Water", 20.0, "ben", 5),
+            item("Rent", 800.0, "MB", 5),
+        ];
+        assert_eq!(Report::new(&items, 2023, 5).settlement(), None);
+        assert_eq!(Report::new(&items, 2023, 6).settlement(), None);
+    }
+
+    #[test]
+    fn writes_html() {
+        let html = report().html();
+        assert!(html.contains("<title>May 2023</title>"));
+        assert!(html.contains(
+            "<tr><td>2023-05-01</td><td>Rent &amp; more</td><td>bills</td>\
+             <td class=\"num\">800.00</td><td>MB</td><td class=\"num\">0.5</td>\
+             <td class=\"num\">400.00</td></tr>"
+        ));
+        assert!(!html.contains("Train"));
+        assert!(html.contains(
+            "<tr><td>anna</td><td class=\"num\">60.00</td><td class=\"num\">30.00</td></tr>"
+        ));
+        assert!(html.contains("<p>Total spent: 860.00</p>"));
+        assert!(html.contains("<p><strong>anna owes MB 370.00</strong></p>"));
+        assert!(html.contains("<tr><td>bills</td><td class=\"num\">800.00</td></tr>"));
+        assert!(html.contains("<svg"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn breaks_long_reports_into_pages() {
+        let items: Vec<FinItem> = (0..100)
+            .map(|i| item(&format!("Item {i}"), 1.0, "MB", 5))
+            .collect();
+        let pdf = String::from_utf8(Report::new(&items, 2023, 5).pdf()).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("/Count 3 >>"));
+        assert!(pdf.ends_with("%%EOF\n"));
+
+        let pdf = String::from_utf8(report().pdf()).unwrap();
+        assert!(pdf.contains("/Count 1 >>"));
+    }
+
+    #[test]
+    fn truncates_long_text() {
+        assert_eq!(truncate("Groceries", 9), "Groceries");
+        assert_eq!(truncate("Groceries", 5), "Groc…");
+    }
+}
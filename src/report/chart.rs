@@ -0,0 +1,287 @@
+use crate::common::escape_xml;
+use crate::model::FinItem;
+
+/// RGB color
+pub(crate) type Color = (u8, u8, u8);
+
+/// Color of the bars, the egui plot default
+const BAR_COLOR: Color = (110, 150, 230);
+/// Color of the line, same as in the bottom panel
+const LINE_COLOR: Color = (100, 200, 100);
+const AVERAGE_COLOR: Color = (230, 120, 60);
+const TEXT_COLOR: Color = (40, 40, 40);
+const GRID_COLOR: Color = (210, 210, 210);
+
+/// Horizontal alignment of text relative to its position
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+/// Drawing primitive, coordinates start at the top left
+pub(crate) enum Shape {
+    Rect {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: Color,
+    },
+    Line {
+        points: Vec<(f32, f32)>,
+        width: f32,
+        color: Color,
+    },
+    /// `y` is the baseline
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        text: String,
+        anchor: Anchor,
+        color: Color,
+    },
+}
+
+/// A chart as a list of shapes, so it can be rendered to any backend
+pub(crate) struct Chart {
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+    pub(crate) shapes: Vec<Shape>,
+}
+
+/// The bottom panel chart: expenses as bars and line, and their average
+pub(crate) fn month_chart(title: &str, items: &[FinItem], width: f32, height: f32) -> Chart {
+    let mut shapes = Vec::new();
+    let values: Vec<f32> = items.iter().map(|i| i.price).collect();
+    let average = if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    };
+
+    // title and legend
+    shapes.push(Shape::Text {
+        x: width / 2.0,
+        y: 20.0,
+        size: 14.0,
+        text: title.to_owned(),
+        anchor: Anchor::Middle,
+        color: TEXT_COLOR,
+    });
+    let legend = [
+        (format!("{title} (bars)"), BAR_COLOR),
+        (format!("{title} (line)"), LINE_COLOR),
+        ("Average".to_owned(), AVERAGE_COLOR),
+    ];
+    for (i, (label, color)) in legend.into_iter().enumerate() {
+        let y = 38.0 + i as f32 * 14.0;
+        shapes.push(Shape::Rect {
+            x: width - 150.0,
+            y: y - 8.0,
+            w: 10.0,
+            h: 8.0,
+            color,
+        });
+        shapes.push(Shape::Text {
+            x: width - 135.0,
+            y,
+            size: 9.0,
+            text: label,
+            anchor: Anchor::Start,
+            color: TEXT_COLOR,
+        });
+    }
+
+    // plot area
+    let left = 50.0;
+    let right = width - 160.0;
+    let top = 40.0;
+    let bottom = height - 30.0;
+    let max = values.iter().cloned().fold(0.0_f32, f32::max).max(1.0);
+    let step = nice_step(max / 4.0);
+    let y_max = (max / step).ceil() * step;
+    let to_y = |v: f32| bottom - (v.max(0.0) / y_max) * (bottom - top);
+
+    // grid with labels
+    for i in 0..=(y_max / step).round() as u32 {
+        let tick = i as f32 * step;
+        let y = to_y(tick);
+        shapes.push(Shape::Line {
+            points: vec![(left, y), (right, y)],
+            width: 0.5,
+            color: GRID_COLOR,
+        });
+        shapes.push(Shape::Text {
+            x: left - 5.0,
+            y: y + 3.0,
+            size: 8.0,
+            text: format!("{tick}"),
+            anchor: Anchor::End,
+            color: TEXT_COLOR,
+        });
+    }
+
+    if !values.is_empty() {
+        let slot = (right - left) / values.len() as f32;
+        let center = |i: usize| left + slot * (i as f32 + 0.5);
+
+        for (i, (item, value)) in items.iter().zip(&values).enumerate() {
+            let y = to_y(*value);
+            shapes.push(Shape::Rect {
+                x: center(i) - slot * 0.35,
+                y,
+                w: slot * 0.7,
+                h: bottom - y,
+                color: BAR_COLOR,
+            });
+            shapes.push(Shape::Text {
+                x: center(i),
+                y: bottom + 12.0,
+                size: 7.0,
+                text: item.date.format("%d").to_string(),
+                anchor: Anchor::Middle,
+                color: TEXT_COLOR,
+            });
+        }
+        shapes.push(Shape::Line {
+            points: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (center(i), to_y(*v)))
+                .collect(),
+            width: 1.5,
+            color: LINE_COLOR,
+        });
+        shapes.push(Shape::Line {
+            points: vec![(left, to_y(average)), (right, to_y(average))],
+            width: 1.5,
+            color: AVERAGE_COLOR,
+        });
+    }
+
+    // axes
+    shapes.push(Shape::Line {
+        points: vec![(left, top), (left, bottom), (right, bottom)],
+        width: 1.0,
+        color: TEXT_COLOR,
+    });
+
+    Chart {
+        width,
+        height,
+        shapes,
+    }
+}
+
+/// Round a grid step to 1, 2 or 5 times a power of ten
+fn nice_step(raw: f32) -> f32 {
+    let magnitude = 10_f32.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+impl Chart {
+    /// Render the chart as a standalone svg document
+    pub(crate) fn svg(&self) -> String {
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif">"#,
+            w = self.width,
+            h = self.height
+        );
+        svg.push_str(r#"<rect width="100%" height="100%" fill="white"/>"#);
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { x, y, w, h, color } => svg.push_str(&format!(
+                    r#"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="{}"/>"#,
+                    hex(*color)
+                )),
+                Shape::Line {
+                    points,
+                    width,
+                    color,
+                } => {
+                    let points: Vec<String> =
+                        points.iter().map(|(x, y)| format!("{x},{y}")).collect();
+                    svg.push_str(&format!(
+                        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{width}"/>"#,
+                        points.join(" "),
+                        hex(*color)
+                    ));
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    text,
+                    anchor,
+                    color,
+                } => {
+                    let anchor = match anchor {
+                        Anchor::Start => "start",
+                        Anchor::Middle => "middle",
+                        Anchor::End => "end",
+                    };
+                    svg.push_str(&format!(
+                        r#"<text x="{x}" y="{y}" font-size="{size}" text-anchor="{anchor}" fill="{}">{}</text>"#,
+                        hex(*color),
+                        escape_xml(text)
+                    ));
+                }
+            }
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// Rasterize the chart off-screen as a png, `scale` pixels per point.
+    /// Text uses the egui default fonts, so no system fonts are needed.
+    pub(crate) fn png(&self, scale: f32) -> Result<Vec<u8>, String> {
+        use resvg::{tiny_skia, usvg};
+
+        let mut options = usvg::Options::default();
+        let fontdb = options.fontdb_mut();
+        for (name, data) in egui::FontDefinitions::default().font_data {
+            let source = usvg::fontdb::Source::Binary(std::sync::Arc::new(data.font.into_owned()));
+            let ids = fontdb.load_font_source(source);
+            if name == "Ubuntu-Light" {
+                if let Some(family) = ids
+                    .first()
+                    .and_then(|id| fontdb.face(*id))
+                    .and_then(|face| face.families.first())
+                {
+                    let family = family.0.clone();
+                    fontdb.set_sans_serif_family(family);
+                }
+            }
+        }
+
+        let tree = usvg::Tree::from_str(&self.svg(), &options).map_err(|e| e.to_string())?;
+        let mut pixmap = tiny_skia::Pixmap::new(
+            (self.width * scale).ceil() as u32,
+            (self.height * scale).ceil() as u32,
+        )
+        .ok_or("Invalid chart size")?;
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+        pixmap.encode_png().map_err(|e| e.to_string())
+    }
+}
+
+fn hex((r, g, b): Color) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
@@ -0,0 +1,206 @@
+use crate::report::chart::{Anchor, Chart, Color, Shape};
+
+/// A4 in points
+pub(crate) const PAGE_WIDTH: f32 = 595.0;
+pub(crate) const PAGE_HEIGHT: f32 = 842.0;
+
+/// A minimal PDF writer using the standard Helvetica fonts.
+/// Coordinates start at the top left of the page.
+pub(crate) struct Pdf {
+    pages: Vec<String>,
+}
+
+impl Pdf {
+    pub(crate) fn new() -> Self {
+        Pdf {
+            pages: vec![String::new()],
+        }
+    }
+
+    pub(crate) fn new_page(&mut self) {
+        self.pages.push(String::new());
+    }
+
+    fn page(&mut self) -> &mut String {
+        self.pages.last_mut().expect("at least one page")
+    }
+
+    /// Draw text with its baseline at `y`
+    pub(crate) fn text(&mut self, x: f32, y: f32, size: f32, bold: bool, text: &str) {
+        let font = if bold { "F2" } else { "F1" };
+        let encoded = encode(text);
+        let y = PAGE_HEIGHT - y;
+        self.page().push_str(&format!(
+            "BT /{font} {size} Tf {x} {y} Td <{encoded}> Tj ET\n"
+        ));
+    }
+
+    pub(crate) fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        let y = PAGE_HEIGHT - y - h;
+        let color = rgb(color);
+        self.page()
+            .push_str(&format!("{color} rg {x} {y} {w} {h} re f\n"));
+    }
+
+    pub(crate) fn line(&mut self, points: &[(f32, f32)], width: f32, color: Color) {
+        let Some(((x0, y0), rest)) = points.split_first() else {
+            return;
+        };
+        let color = rgb(color);
+        let mut path = format!("{color} RG {width} w {x0} {} m", PAGE_HEIGHT - y0);
+        for (x, y) in rest {
+            path.push_str(&format!(" {x} {} l", PAGE_HEIGHT - y));
+        }
+        path.push_str(" S\n");
+        self.page().push_str(&path);
+    }
+
+    /// Draw a chart with its top left corner at the given position
+    pub(crate) fn chart(&mut self, chart: &Chart, left: f32, top: f32) {
+        for shape in &chart.shapes {
+            match shape {
+                Shape::Rect { x, y, w, h, color } => self.rect(left + x, top + y, *w, *h, *color),
+                Shape::Line {
+                    points,
+                    width,
+                    color,
+                } => {
+                    let points: Vec<(f32, f32)> =
+                        points.iter().map(|(x, y)| (left + x, top + y)).collect();
+                    self.line(&points, *width, *color);
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    text,
+                    anchor,
+                    color,
+                } => {
+                    let offset = match anchor {
+                        Anchor::Start => 0.0,
+                        Anchor::Middle => text_width(text, *size) / 2.0,
+                        Anchor::End => text_width(text, *size),
+                    };
+                    let color = rgb(*color);
+                    self.page().push_str(&format!("{color} rg\n"));
+                    self.text(left + x - offset, top + y, *size, false, text);
+                    self.page().push_str("0 0 0 rg\n");
+                }
+            }
+        }
+    }
+
+    /// Serialize the document
+    pub(crate) fn finish(self) -> Vec<u8> {
+        let mut objects: Vec<String> = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            String::new(), // pages, filled in below
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_owned(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_owned(),
+        ];
+
+        let mut kids = Vec::new();
+        for content in &self.pages {
+            // page object followed by its content stream
+            let page_id = objects.len() + 1;
+            kids.push(format!("{page_id} 0 R"));
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                page_id + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{content}endstream",
+                content.len()
+            ));
+        }
+        objects[1] = format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        );
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.push_str(&format!("{} 0 obj\n{object}\nendobj\n", i + 1));
+        }
+        let xref = out.len();
+        out.push_str(&format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            objects.len() + 1
+        ));
+        for offset in offsets {
+            out.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        out.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        ));
+        out.into_bytes()
+    }
+}
+
+/// Approximate width of Helvetica text
+pub(crate) fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().count() as f32 * size * 0.5
+}
+
+/// Hex encode text as WinAnsi, unsupported characters become '?'
+fn encode(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '€' => 0x80,
+            '…' => 0x85,
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u32 as u8,
+            _ => b'?',
+        })
+        .map(|b| format!("{b:02X}"))
+        .collect()
+}
+
+fn rgb((r, g, b): Color) -> String {
+    format!(
+        "{:.3} {:.3} {:.3}",
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_win_ansi() {
+        assert_eq!(encode("A€ä…✓"), "4180E4853F");
+    }
+
+    #[test]
+    fn points_the_xref_at_the_objects() {
+        let mut pdf = Pdf::new();
+        pdf.text(40.0, 42.0, 12.0, true, "Total");
+        pdf.new_page();
+        pdf.rect(0.0, 0.0, 10.0, 10.0, (255, 0, 0));
+        let out = String::from_utf8(pdf.finish()).unwrap();
+
+        assert!(out.contains("/Kids [5 0 R 7 0 R] /Count 2"));
+        // text is placed from the bottom of the page
+        assert!(out.contains("BT /F2 12 Tf 40 800 Td <546F74616C> Tj ET\n"));
+        assert!(out.contains("1.000 0.000 0.000 rg 0 832 10 10 re f\n"));
+
+        let xref = out.rfind("startxref\n").unwrap();
+        let start: usize = out[xref + 10..].lines().next().unwrap().parse().unwrap();
+        assert!(out[start..].starts_with("xref\n0 9\n"));
+        let offsets = out[start..].lines().skip(3).take(8);
+        for (i, line) in offsets.enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(out[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+    }
+}
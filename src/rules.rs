@@ -0,0 +1,266 @@
+use log::warn;
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+
+use crate::model::FinItem;
+
+/// How the pattern of a rule is compared with the item text
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchKind {
+    Contains,
+    Regex,
+}
+
+impl MatchKind {
+    pub(crate) const ALL: [MatchKind; 2] = [MatchKind::Contains, MatchKind::Regex];
+}
+
+impl fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MatchKind::Contains => "Contains",
+            MatchKind::Regex => "Regex",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Sets fields of items whose text, amount and account match
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Rule {
+    pub(crate) name: String,
+    pub(crate) enabled: bool,
+
+    // conditions, empty ones match everything
+    /// Compared case-insensitively with the item (payee) and memo
+    pub(crate) pattern: String,
+    pub(crate) match_kind: MatchKind,
+    pub(crate) min_amount: Option<f32>,
+    pub(crate) max_amount: Option<f32>,
+    /// Substring of the bank account
+    pub(crate) account: String,
+
+    // actions
+    pub(crate) category: Option<String>,
+    pub(crate) owner: Option<String>,
+    pub(crate) ratio: Option<f32>,
+    /// Comma separated tags to add
+    pub(crate) tags: String,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule {
+            name: "New rule".to_owned(),
+            enabled: true,
+            pattern: String::new(),
+            match_kind: MatchKind::Contains,
+            min_amount: None,
+            max_amount: None,
+            account: String::new(),
+            category: None,
+            owner: None,
+            ratio: None,
+            tags: String::new(),
+        }
+    }
+}
+
+impl Rule {
+    /// The compiled pattern, None for an empty one
+    pub(crate) fn regex(&self) -> Result<Option<Regex>, regex::Error> {
+        if self.pattern.is_empty() {
+            return Ok(None);
+        }
+        let pattern = match self.match_kind {
+            MatchKind::Contains => regex::escape(&self.pattern),
+            MatchKind::Regex => self.pattern.clone(),
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Some)
+    }
+
+    fn matches(&self, regex: Option<&Regex>, item: &FinItem) -> bool {
+        let text_matches = regex.map_or(true, |r| {
+            r.is_match(&item.item) || item.memo.as_deref().map_or(false, |m| r.is_match(m))
+        });
+        let account_matches = self.account.is_empty()
+            || item.account.as_deref().map_or(false, |a| {
+                a.to_lowercase().contains(&self.account.to_lowercase())
+            });
+        text_matches
+            && account_matches
+            && self.min_amount.map_or(true, |min| item.price >= min)
+            && self.max_amount.map_or(true, |max| item.price <= max)
+    }
+
+    fn apply(&self, item: &mut FinItem) {
+        if let Some(category) = &self.category {
+            item.category = Some(category.clone());
+        }
+        if let Some(owner) = &self.owner {
+            item.owner = owner.clone();
+        }
+        if let Some(ratio) = self.ratio {
+            item.ratio = ratio;
+        }
+        for tag in self
+            .tags
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        {
+            if !item.tags.iter().any(|t| t == tag) {
+                item.tags.push(tag.to_owned());
+            }
+        }
+    }
+}
+
+/// Apply the enabled rules in order, later rules override earlier ones.
+/// Returns the number of items matched by any rule.
+pub(crate) fn apply_rules<'a>(
+    rules: &[Rule],
+    items: impl IntoIterator<Item = &'a mut FinItem>,
+) -> usize {
+    let compiled: Vec<(&Rule, Option<Regex>)> = rules
+        .iter()
+        .filter(|r| r.enabled)
+        .filter_map(|r| match r.regex() {
+            Ok(regex) => Some((r, regex)),
+            Err(e) => {
+                warn!("Skipping rule {}: {}", r.name, e);
+                None
+            }
+        })
+        .collect();
+
+    let mut matched = 0;
+    for item in items {
+        let mut any = false;
+        for (rule, regex) in &compiled {
+            if rule.matches(regex.as_ref(), item) {
+                rule.apply(item);
+                any = true;
+            }
+        }
+        matched += usize::from(any);
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn item(name: &str, price: f32) -> FinItem {
+        FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        )
+    }
+
+    fn rule(pattern: &str, match_kind: MatchKind) -> Rule {
+        Rule {
+            pattern: pattern.to_owned(),
+            match_kind,
+            category: Some("Groceries".to_owned()),
+            ..Rule::default()
+        }
+    }
+
+    #[test]
+    fn matches_text_the_item_contains() {
+        let rules = [rule("rewe", MatchKind::Contains)];
+        let mut items = [item("REWE Markt 123", 20.0), item("Aldi", 15.0)];
+        assert_eq!(apply_rules(&rules, &mut items), 1);
+        assert_eq!(items[0].category.as_deref(), Some("Groceries"));
+        assert_eq!(items[1].category.as_deref(), Some("category"));
+
+        // the pattern is text, not a regex
+        let rules = [rule("re.e", MatchKind::Contains)];
+        assert_eq!(apply_rules(&rules, [&mut item("REWE", 20.0)]), 0);
+
+        // the memo is searched too
+        let mut paid = item("Card payment", 20.0);
+        paid.memo = Some("Rewe Berlin".to_owned());
+        let rules = [rule("rewe", MatchKind::Contains)];
+        assert_eq!(apply_rules(&rules, [&mut paid]), 1);
+    }
+
+    #[test]
+    fn matches_regexes() {
+        let rules = [rule("^(rewe|aldi)\\b", MatchKind::Regex)];
+        let mut items = [item("Aldi Süd", 15.0), item("Bio Rewe", 20.0)];
+        assert_eq!(apply_rules(&rules, &mut items), 1);
+        assert_eq!(items[0].category.as_deref(), Some("Groceries"));
+
+        // an invalid regex is skipped
+        let rules = [rule("(rewe", MatchKind::Regex)];
+        assert_eq!(apply_rules(&rules, [&mut item("rewe", 1.0)]), 0);
+    }
+
+    #[test]
+    fn matches_amounts_within_the_bounds() {
+        let rules = [Rule {
+            min_amount: Some(10.0),
+            max_amount: Some(20.0),
+            ..rule("", MatchKind::Contains)
+        }];
+        let mut items = [
+            item("a", 9.99),
+            item("b", 10.0),
+            item("c", 20.0),
+            item("d", 20.01),
+        ];
+        assert_eq!(apply_rules(&rules, &mut items), 2);
+        assert!(items[1].category == items[2].category);
+        assert_eq!(items[3].category.as_deref(), Some("category"));
+    }
+
+    #[test]
+    fn matches_the_account_and_skips_disabled_rules() {
+        let mut rules = [Rule {
+            account: "giro".to_owned(),
+            ..rule("", MatchKind::Contains)
+        }];
+        let mut giro = item("a", 1.0);
+        giro.account = Some("DE12 GIRO".to_owned());
+        let mut items = [giro, item("b", 1.0)];
+        assert_eq!(apply_rules(&rules, &mut items), 1);
+
+        rules[0].enabled = false;
+        assert_eq!(apply_rules(&rules, &mut items), 0);
+    }
+
+    #[test]
+    fn adds_tags_once_and_later_rules_win() {
+        let rules = [
+            Rule {
+                tags: "food, weekly,".to_owned(),
+                owner: Some("anna".to_owned()),
+                ..rule("rewe", MatchKind::Contains)
+            },
+            Rule {
+                tags: "food".to_owned(),
+                owner: Some("ben".to_owned()),
+                ratio: Some(1.0),
+                category: None,
+                ..rule("rewe", MatchKind::Contains)
+            },
+        ];
+        let mut rewe = item("Rewe", 20.0);
+        rewe.tags = vec!["weekly".to_owned()];
+        assert_eq!(apply_rules(&rules, [&mut rewe]), 1);
+        assert_eq!(rewe.tags, ["weekly", "food"]);
+        assert_eq!(rewe.owner, "ben");
+        assert_eq!(rewe.ratio, 1.0);
+        assert_eq!(rewe.category.as_deref(), Some("Groceries"));
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::model::FinItem;
+use crate::rules::{self, Rule};
+
+/// Words that set the date, completed in the quick-add bar
+const DATE_WORDS: [&str; 9] = [
+    "today",
+    "yesterday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Most suggestions shown for the word being typed
+const MAX_SUGGESTIONS: usize = 6;
+
+/// Parse a quick-add line like `12.50 groceries Rewe @anna 50% yesterday`
+///
+/// The first number is the price, `@name` the owner, `50%` the ratio and
+/// `#category` or a known category the category. Dates are `today`,
+/// `yesterday`, a weekday written out within the last week, `2024-01-31` or
+/// `31.1.`.
+/// The remaining words make up the item. The rules fill in the fields that
+/// are not typed. Returns None without a price.
+pub(crate) fn parse(
+    input: &str,
+    today: NaiveDate,
+    categories: &[String],
+    rules: &[Rule],
+) -> Option<FinItem> {
+    let mut price = None;
+    let mut date = None;
+    let mut category = None;
+    let mut owner = None;
+    let mut ratio = None;
+    let mut words = Vec::new();
+
+    for word in input.split_whitespace() {
+        if let Some(name) = word.strip_prefix('@').filter(|n| !n.is_empty()) {
+            owner = Some(name.to_owned());
+        } else if let Some(name) = word.strip_prefix('#').filter(|n| !n.is_empty()) {
+            category = Some(known_category(name, categories).unwrap_or_else(|| name.to_owned()));
+        } else if let Some(percent) = word.strip_suffix('%').and_then(parse_number) {
+            ratio = Some((percent / 100.0).clamp(0.0, 1.0));
+        } else if let (None, Some(number)) = (price, parse_number(word)) {
+            price = Some(number);
+        } else if let (None, Some(day)) = (date, parse_date(word, today)) {
+            date = Some(day);
+        } else if let (None, Some(known)) = (&category, known_category(word, categories)) {
+            category = Some(known);
+        } else {
+            words.push(word);
+        }
+    }
+
+    let name = if words.is_empty() {
+        category.clone().unwrap_or_else(|| "item".to_owned())
+    } else {
+        words.join(" ")
+    };
+    let mut item = FinItem::new(date.unwrap_or(today), name, price?);
+    rules::apply_rules(rules, [&mut item]);
+    if category.is_some() {
+        item.category = category;
+    }
+    if let Some(owner) = owner {
+        item.owner = owner;
+    }
+    if let Some(ratio) = ratio {
+        item.ratio = ratio;
+    }
+    Some(item)
+}
+
+/// Completions of the word being typed, as complete lines
+pub(crate) fn suggestions(input: &str, categories: &[String], owners: &[String]) -> Vec<String> {
+    if input.ends_with(char::is_whitespace) {
+        return Vec::new();
+    }
+    let Some(word) = input.split_whitespace().last() else {
+        return Vec::new();
+    };
+    let head = &input[..input.len() - word.len()];
+
+    let matching = |prefix: &str, candidates: &mut dyn Iterator<Item = &str>| -> Vec<String> {
+        let prefix = prefix.to_lowercase();
+        candidates
+            .filter(|c| c.to_lowercase().starts_with(&prefix) && c.len() > prefix.len())
+            .map(str::to_owned)
+            .collect()
+    };
+    let completions: Vec<String> = if let Some(prefix) = word.strip_prefix('@') {
+        matching(prefix, &mut owners.iter().map(String::as_str))
+            .into_iter()
+            .map(|o| format!("@{o}"))
+            .collect()
+    } else if let Some(prefix) = word.strip_prefix('#') {
+        matching(prefix, &mut categories.iter().map(String::as_str))
+            .into_iter()
+            .map(|c| format!("#{c}"))
+            .collect()
+    } else {
+        let mut candidates = categories
+            .iter()
+            .map(String::as_str)
+            .chain(DATE_WORDS.iter().copied());
+        matching(word, &mut candidates)
+    };
+
+    completions
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|c| format!("{head}{c} "))
+        .collect()
+}
+
+fn known_category(word: &str, categories: &[String]) -> Option<String> {
+    categories
+        .iter()
+        .find(|c| c.eq_ignore_ascii_case(word))
+        .cloned()
+}
+
+/// A price with a decimal point or comma and an optional currency sign
+fn parse_number(word: &str) -> Option<f32> {
+    let word = word.trim_start_matches('€').trim_end_matches('€');
+    word.replace(',', ".")
+        .parse::<f32>()
+        .ok()
+        .filter(|n| n.is_finite())
+}
+
+fn parse_date(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let word = word.to_lowercase();
+    match word.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return Some(today - Duration::days(1)),
+        _ => {}
+    }
+
+    // the last such weekday, today included. Abbreviations like `sa` or
+    // `sun` are left to the item text.
+    let weekday = Some(&word)
+        .filter(|w| DATE_WORDS.contains(&w.as_str()))
+        .and_then(|w| w.parse::<Weekday>().ok());
+    if let Some(weekday) = weekday {
+        let days_back =
+            (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        return Some(today - Duration::days(days_back as i64));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(&word, "%Y-%m-%d") {
+        return Some(date);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&word, "%d.%m.%Y") {
+        return Some(date);
+    }
+    // day and month of the current year
+    let (day, month) = word.strip_suffix('.')?.split_once('.')?;
+    NaiveDate::from_ymd_opt(today.year(), month.parse().ok()?, day.parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        // a Wednesday
+        NaiveDate::from_ymd_opt(2023, 5, 10).unwrap()
+    }
+
+    #[test]
+    fn parses_a_quick_add_line() {
+        let categories = ["Groceries".to_owned()];
+        let item = parse(
+            "12,50 groceries Rewe @anna 50% yesterday",
+            today(),
+            &categories,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(item.price, 12.5);
+        assert_eq!(item.item, "Rewe");
+        assert_eq!(item.category.as_deref(), Some("Groceries"));
+        assert_eq!(item.owner, "anna");
+        assert_eq!(item.ratio, 0.5);
+        assert_eq!(item.date, NaiveDate::from_ymd_opt(2023, 5, 9).unwrap());
+        assert!(parse("groceries", today(), &categories, &[]).is_none());
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(
+            parse_date("Monday", today()),
+            NaiveDate::from_ymd_opt(2023, 5, 8)
+        );
+        assert_eq!(parse_date("wednesday", today()), Some(today()));
+        assert_eq!(
+            parse_date("2023-01-31", today()),
+            NaiveDate::from_ymd_opt(2023, 1, 31)
+        );
+        assert_eq!(
+            parse_date("31.1.", today()),
+            NaiveDate::from_ymd_opt(2023, 1, 31)
+        );
+        assert_eq!(
+            parse_date("1.2.2022", today()),
+            NaiveDate::from_ymd_opt(2022, 2, 1)
+        );
+    }
+
+    #[test]
+    fn keeps_abbreviated_weekdays_in_the_item() {
+        for word in ["mo", "tu", "sa", "su", "sun", "sat"] {
+            assert_eq!(parse_date(word, today()), None, "{}", word);
+        }
+        let item = parse("4 sun cream", today(), &[], &[]).unwrap();
+        assert_eq!(item.item, "sun cream");
+        assert_eq!(item.date, today());
+    }
+
+    #[test]
+    fn keeps_typed_fields_over_rules() {
+        let rule = Rule {
+            pattern: "rewe".to_owned(),
+            category: Some("Groceries".to_owned()),
+            owner: Some("ben".to_owned()),
+            ratio: Some(1.0),
+            tags: "food".to_owned(),
+            ..Rule::default()
+        };
+        let rules = [rule];
+
+        let item = parse("12 Rewe", today(), &[], &rules).unwrap();
+        assert_eq!(item.category.as_deref(), Some("Groceries"));
+        assert_eq!(item.owner, "ben");
+        assert_eq!(item.ratio, 1.0);
+
+        let item = parse("12 Rewe #Party @anna 50%", today(), &[], &rules).unwrap();
+        assert_eq!(item.category.as_deref(), Some("Party"));
+        assert_eq!(item.owner, "anna");
+        assert_eq!(item.ratio, 0.5);
+        assert_eq!(item.tags, ["food"]);
+    }
+}
@@ -0,0 +1,483 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rusqlite::{params, params_from_iter, Connection, Row, Transaction};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::ledger::{Ledger, LedgerError};
+use crate::model::{FinItem, Recurrence};
+
+/// File extension of ledger documents stored in SQLite
+pub(crate) const EXTENSION: &str = "sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS items (
+    id TEXT PRIMARY KEY,
+    date TEXT NOT NULL,
+    item TEXT NOT NULL,
+    category TEXT,
+    price REAL NOT NULL,
+    owner TEXT NOT NULL,
+    ratio REAL NOT NULL,
+    memo TEXT,
+    fitid TEXT,
+    recurrence TEXT,
+    account TEXT,
+    tags TEXT NOT NULL DEFAULT '[]'
+);
+CREATE INDEX IF NOT EXISTS items_date ON items (date);
+CREATE INDEX IF NOT EXISTS items_category ON items (category, date);
+CREATE INDEX IF NOT EXISTS items_owner ON items (owner, date);
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS history (
+    id TEXT PRIMARY KEY,
+    time TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS trash (
+    id TEXT PRIMARY KEY,
+    time TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+";
+
+const COLUMNS: &str =
+    "id, date, item, category, price, owner, ratio, memo, fitid, recurrence, account, tags";
+
+/// Which items to query, None matches everything
+#[derive(Default)]
+pub(crate) struct ItemFilter {
+    pub(crate) from: Option<NaiveDate>,
+    /// Exclusive
+    pub(crate) to: Option<NaiveDate>,
+    pub(crate) category: Option<String>,
+    pub(crate) owner: Option<String>,
+}
+
+/// A ledger document in a SQLite database. Only rows that changed since the
+/// last write are written.
+pub(crate) struct SqliteStore {
+    conn: Connection,
+    /// The items as last read or written
+    written: HashMap<Uuid, FinItem>,
+    /// The settings as last read or written, as json
+    settings: HashMap<&'static str, String>,
+    /// The rows of the activity log and the trash as last read or written
+    history: JsonRows,
+    trash: JsonRows,
+}
+
+/// A table of values by id as json, e.g. the changes of the activity log.
+/// Only rows that changed since the last write are written.
+struct JsonRows {
+    table: &'static str,
+    written: HashMap<Uuid, String>,
+}
+
+impl JsonRows {
+    fn new(table: &'static str) -> Self {
+        JsonRows {
+            table,
+            written: HashMap::new(),
+        }
+    }
+
+    /// All values ordered by time
+    fn load<T: serde::de::DeserializeOwned>(
+        &mut self,
+        conn: &Connection,
+    ) -> Result<Vec<T>, LedgerError> {
+        let rows: Vec<(String, String)> = conn
+            .prepare(&format!(
+                "SELECT id, value FROM {} ORDER BY time",
+                self.table
+            ))?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        self.written.clear();
+        let mut values = Vec::with_capacity(rows.len());
+        for (id, value) in rows {
+            values.push(serde_json::from_str(&value)?);
+            let id = id
+                .parse()
+                .map_err(|e: uuid::Error| LedgerError(e.to_string()))?;
+            self.written.insert(id, value);
+        }
+        Ok(values)
+    }
+
+    /// Write the rows that changed and delete the missing ones. Returns the
+    /// rows as written, to be kept once the transaction is committed.
+    fn write(
+        &self,
+        tx: &Transaction<'_>,
+        rows: Vec<(Uuid, NaiveDateTime, String)>,
+        changed: &mut usize,
+    ) -> Result<HashMap<Uuid, String>, LedgerError> {
+        let mut upsert = tx.prepare_cached(&format!(
+            "INSERT OR REPLACE INTO {} (id, time, value) VALUES (?1, ?2, ?3)",
+            self.table
+        ))?;
+        let mut written = HashMap::with_capacity(rows.len());
+        for (id, time, value) in rows {
+            if self.written.get(&id) != Some(&value) {
+                upsert.execute(params![id.to_string(), time.to_string(), value])?;
+                *changed += 1;
+            }
+            written.insert(id, value);
+        }
+
+        let mut delete = tx.prepare_cached(&format!("DELETE FROM {} WHERE id = ?1", self.table))?;
+        for id in self.written.keys().filter(|id| !written.contains_key(id)) {
+            delete.execute(params![id.to_string()])?;
+            *changed += 1;
+        }
+        Ok(written)
+    }
+}
+
+impl From<rusqlite::Error> for LedgerError {
+    fn from(e: rusqlite::Error) -> Self {
+        LedgerError(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for LedgerError {
+    fn from(e: serde_json::Error) -> Self {
+        LedgerError(e.to_string())
+    }
+}
+
+/// Whether the document is stored in SQLite
+pub(crate) fn is_store(path: &Path) -> bool {
+    path.extension().map_or(false, |e| e == EXTENSION)
+}
+
+impl SqliteStore {
+    /// Open or create the database
+    pub(crate) fn open(path: &Path) -> Result<Self, LedgerError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteStore {
+            conn,
+            written: HashMap::new(),
+            settings: HashMap::new(),
+            history: JsonRows::new("history"),
+            trash: JsonRows::new("trash"),
+        })
+    }
+
+    /// Read the whole ledger
+    pub(crate) fn load(&mut self) -> Result<Ledger, LedgerError> {
+        let items = self.query(&ItemFilter::default())?;
+        self.written = items.iter().map(|i| (i.id, i.clone())).collect();
+
+        let mut settings: HashMap<String, String> = self
+            .conn
+            .prepare("SELECT key, value FROM settings")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        let mut setting = |key: &'static str| -> Option<String> {
+            let value = settings.remove(key)?;
+            self.settings.insert(key, value.clone());
+            Some(value)
+        };
+
+        Ok(Ledger {
+            items,
+            categories: setting("categories")
+                .map(|v| serde_json::from_str(&v))
+                .transpose()?
+                .unwrap_or_default(),
+            rules: setting("rules")
+                .map(|v| serde_json::from_str(&v))
+                .transpose()?
+                .unwrap_or_default(),
+            templates: setting("templates")
+                .map(|v| serde_json::from_str(&v))
+                .transpose()?
+                .unwrap_or_default(),
+            history: self.history.load(&self.conn)?,
+            trash: self.trash.load(&self.conn)?,
+        })
+    }
+
+    /// Write what changed since the last write in one transaction.
+    /// Returns the number of changed rows.
+    pub(crate) fn write(&mut self, ledger: &Ledger) -> Result<usize, LedgerError> {
+        let tx = self.conn.transaction()?;
+        let mut changed = 0;
+        let mut settings = Vec::new();
+
+        {
+            let mut upsert = tx.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO items ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+            ))?;
+            for item in &ledger.items {
+                if self.written.get(&item.id) == Some(item) {
+                    continue;
+                }
+                upsert.execute(params![
+                    item.id.to_string(),
+                    item.date.to_string(),
+                    item.item,
+                    item.category,
+                    item.price,
+                    item.owner,
+                    item.ratio,
+                    item.memo,
+                    item.fitid,
+                    item.recurrence.map(|r| r.to_string()),
+                    item.account,
+                    serde_json::to_string(&item.tags)?,
+                ])?;
+                changed += 1;
+            }
+
+            let current: HashMap<Uuid, &FinItem> = ledger.items.iter().map(|i| (i.id, i)).collect();
+            let mut delete = tx.prepare_cached("DELETE FROM items WHERE id = ?1")?;
+            for id in self.written.keys().filter(|id| !current.contains_key(id)) {
+                delete.execute(params![id.to_string()])?;
+                changed += 1;
+            }
+
+            let mut set =
+                tx.prepare_cached("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)")?;
+            for (key, value) in [
+                ("categories", serde_json::to_string(&ledger.categories)?),
+                ("rules", serde_json::to_string(&ledger.rules)?),
+                ("templates", serde_json::to_string(&ledger.templates)?),
+            ] {
+                if self.settings.get(key) != Some(&value) {
+                    set.execute(params![key, value])?;
+                    settings.push((key, value));
+                    changed += 1;
+                }
+            }
+        }
+
+        let history = ledger
+            .history
+            .iter()
+            .map(|c| Ok((c.id, c.time, serde_json::to_string(c)?)))
+            .collect::<Result<_, LedgerError>>()?;
+        let history = self.history.write(&tx, history, &mut changed)?;
+        let trash = ledger
+            .trash
+            .iter()
+            .map(|t| Ok((t.item.id, t.deleted, serde_json::to_string(t)?)))
+            .collect::<Result<_, LedgerError>>()?;
+        let trash = self.trash.write(&tx, trash, &mut changed)?;
+        tx.commit()?;
+
+        self.written = ledger.items.iter().map(|i| (i.id, i.clone())).collect();
+        self.settings.extend(settings);
+        self.history.written = history;
+        self.trash.written = trash;
+        Ok(changed)
+    }
+
+    /// Items matching the filter ordered by date, using the indexes
+    pub(crate) fn query(&self, filter: &ItemFilter) -> Result<Vec<FinItem>, LedgerError> {
+        // only the conditions that are set, so SQLite can use the indexes
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(from) = filter.from {
+            conditions.push("date >= ?");
+            values.push(from.to_string());
+        }
+        if let Some(to) = filter.to {
+            conditions.push("date < ?");
+            values.push(to.to_string());
+        }
+        if let Some(category) = &filter.category {
+            conditions.push("category = ?");
+            values.push(category.clone());
+        }
+        if let Some(owner) = &filter.owner {
+            conditions.push("owner = ?");
+            values.push(owner.clone());
+        }
+        let condition = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let mut statement = self.conn.prepare_cached(&format!(
+            "SELECT {COLUMNS} FROM items {condition} ORDER BY date"
+        ))?;
+        let items = statement
+            .query_map(params_from_iter(values), read_item)?
+            .collect::<Result<_, _>>()?;
+        Ok(items)
+    }
+}
+
+fn read_item(row: &Row<'_>) -> rusqlite::Result<FinItem> {
+    let invalid = |column: usize, e: String| {
+        rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e.into())
+    };
+    let id: String = row.get(0)?;
+    let date: String = row.get(1)?;
+    let recurrence: Option<String> = row.get(9)?;
+    let tags: String = row.get(11)?;
+
+    let mut item = FinItem::new(
+        date.parse()
+            .map_err(|e: chrono::ParseError| invalid(1, e.to_string()))?,
+        row.get(2)?,
+        row.get(4)?,
+    );
+    item.id = id
+        .parse()
+        .map_err(|e: uuid::Error| invalid(0, e.to_string()))?;
+    item.category = row.get(3)?;
+    item.owner = row.get(5)?;
+    item.ratio = row.get(6)?;
+    item.memo = row.get(7)?;
+    item.fitid = row.get(8)?;
+    item.recurrence =
+        recurrence.and_then(|r| Recurrence::ALL.into_iter().find(|a| a.to_string() == r));
+    item.account = row.get(10)?;
+    item.tags = serde_json::from_str(&tags).map_err(|e| invalid(11, e.to_string()))?;
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Change;
+    use crate::trash::Trashed;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 3, day).unwrap()
+    }
+
+    fn ledger() -> Ledger {
+        let mut power = FinItem::new(date(1), "Power".to_owned(), 60.0);
+        power.recurrence = Some(Recurrence::Monthly);
+        power.tags = vec!["home".to_owned()];
+        let mut rent = FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 4, 1).unwrap(),
+            "Rent".to_owned(),
+            500.0,
+        );
+        rent.category = Some("bills".to_owned());
+        rent.owner = "anna".to_owned();
+        let deleted = FinItem::new(date(2), "Cinema".to_owned(), 12.0);
+        Ledger {
+            history: vec![Change {
+                id: Uuid::new_v4(),
+                time: date(1).and_hms_opt(10, 0, 0).unwrap(),
+                user: "anna".to_owned(),
+                item: power.id,
+                before: None,
+                after: Some(power.clone()),
+            }],
+            trash: vec![Trashed {
+                deleted: date(2).and_hms_opt(12, 0, 0).unwrap(),
+                item: deleted,
+            }],
+            items: vec![power, rent],
+            categories: vec!["bills".to_owned()],
+            ..Ledger::default()
+        }
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let mut store = SqliteStore::open(Path::new(":memory:")).unwrap();
+        let ledger = ledger();
+        // items, categories, rules, templates, history and trash
+        assert_eq!(store.write(&ledger).unwrap(), 7);
+        assert!(store.load().unwrap() == ledger);
+    }
+
+    #[test]
+    fn writes_only_changed_rows() {
+        let mut store = SqliteStore::open(Path::new(":memory:")).unwrap();
+        let mut ledger = ledger();
+        store.write(&ledger).unwrap();
+        assert_eq!(store.write(&ledger).unwrap(), 0);
+
+        ledger.items[0].price = 61.0;
+        ledger.history[0].user = "ben".to_owned();
+        let restored = ledger.trash.remove(0);
+        ledger.items.push(restored.item);
+        assert_eq!(store.write(&ledger).unwrap(), 4);
+        // the items are read ordered by date
+        ledger.items.sort_by_key(|i| i.date);
+        assert!(store.load().unwrap() == ledger);
+    }
+
+    #[test]
+    fn queries_by_date() {
+        let mut store = SqliteStore::open(Path::new(":memory:")).unwrap();
+        store.write(&ledger()).unwrap();
+
+        let march = ItemFilter {
+            from: Some(date(1)),
+            to: Some(NaiveDate::from_ymd_opt(2023, 4, 1).unwrap()),
+            ..ItemFilter::default()
+        };
+        let items = store.query(&march).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item, "Power");
+        assert_eq!(store.query(&ItemFilter::default()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn queries_by_category_and_person() {
+        let mut store = SqliteStore::open(Path::new(":memory:")).unwrap();
+        store.write(&ledger()).unwrap();
+
+        let bills = ItemFilter {
+            category: Some("bills".to_owned()),
+            ..ItemFilter::default()
+        };
+        let items = store.query(&bills).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item, "Rent");
+
+        let anna = ItemFilter {
+            owner: Some("anna".to_owned()),
+            ..ItemFilter::default()
+        };
+        assert_eq!(store.query(&anna).unwrap()[0].item, "Rent");
+        let mb_in_march = ItemFilter {
+            to: Some(NaiveDate::from_ymd_opt(2023, 4, 1).unwrap()),
+            owner: Some("MB".to_owned()),
+            ..ItemFilter::default()
+        };
+        assert_eq!(store.query(&mb_in_march).unwrap()[0].item, "Power");
+        let nobody = ItemFilter {
+            category: Some("bills".to_owned()),
+            owner: Some("MB".to_owned()),
+            ..ItemFilter::default()
+        };
+        assert!(store.query(&nobody).unwrap().is_empty());
+    }
+
+    #[test]
+    fn uses_the_indexes() {
+        let store = SqliteStore::open(Path::new(":memory:")).unwrap();
+        let plan = |filter: &str| -> String {
+            let mut statement = store
+                .conn
+                .prepare(&format!(
+                    "EXPLAIN QUERY PLAN SELECT id FROM items WHERE {filter}"
+                ))
+                .unwrap();
+            let rows = statement
+                .query_map([], |row| row.get::<_, String>(3))
+                .unwrap();
+            rows.map(Result::unwrap).collect()
+        };
+        assert!(plan("category = 'bills' AND date >= '2023-03-01'").contains("items_category"));
+        assert!(plan("owner = 'anna'").contains("items_owner"));
+    }
+}
@@ -0,0 +1,409 @@
+use log::{info, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+use crate::app::TemplateApp;
+use crate::ledger::{self, Ledger};
+use crate::model::FinItem;
+
+/// Seconds between checks of the sync folder for changed files
+const POLL_INTERVAL: f64 = 5.0;
+
+/// A ledger synced through a folder shared between devices. Every device
+/// writes its own file, named after the device, and merges the others.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub(crate) struct SyncFolder {
+    pub(crate) folder: PathBuf,
+    /// The items of each device as last merged, the base of the next merge
+    bases: HashMap<String, Vec<FinItem>>,
+    pub(crate) conflicts: Vec<Conflict>,
+    /// Modification times of the merged files
+    #[serde(skip)]
+    seen: HashMap<PathBuf, SystemTime>,
+    /// The ledger as last written to the folder
+    #[serde(skip)]
+    written: Option<Ledger>,
+}
+
+impl SyncFolder {
+    pub(crate) fn new(folder: PathBuf) -> Self {
+        SyncFolder {
+            folder,
+            ..SyncFolder::default()
+        }
+    }
+}
+
+/// An item changed on this and another device since they last merged.
+/// None is a deleted item.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub(crate) struct Conflict {
+    pub(crate) device: String,
+    pub(crate) ours: Option<FinItem>,
+    pub(crate) theirs: Option<FinItem>,
+}
+
+impl Conflict {
+    fn id(&self) -> Option<Uuid> {
+        self.ours.as_ref().or(self.theirs.as_ref()).map(|i| i.id)
+    }
+}
+
+/// What a merge left to do besides taking the changes
+#[derive(Default)]
+pub(crate) struct Merged {
+    pub(crate) conflicts: Vec<Conflict>,
+    /// Items deleted by them, still ours to move to the trash
+    pub(crate) deleted: Vec<Uuid>,
+}
+
+/// Three-way merge of the items of another device into ours, per item id.
+/// Items changed on one side only take that change, items changed on both
+/// sides keep ours and are returned as conflicts.
+pub(crate) fn merge(
+    base: &[FinItem],
+    ours: &mut Vec<FinItem>,
+    theirs: &[FinItem],
+    device: &str,
+) -> Merged {
+    let base: HashMap<Uuid, &FinItem> = base.iter().map(|i| (i.id, i)).collect();
+    let theirs_by_id: HashMap<Uuid, &FinItem> = theirs.iter().map(|i| (i.id, i)).collect();
+    let mut merged = Merged::default();
+    let conflict = |ours: Option<&FinItem>, theirs: Option<&FinItem>| Conflict {
+        device: device.to_owned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    };
+
+    // changed or deleted by them
+    for item in ours.iter_mut() {
+        let before = base.get(&item.id).copied();
+        match (before, theirs_by_id.get(&item.id).copied()) {
+            (_, Some(theirs)) if theirs == item => {}
+            // new on our side, or they have not seen it yet
+            (None, None) => {}
+            (None, Some(theirs)) => merged.conflicts.push(conflict(Some(item), Some(theirs))),
+            (Some(before), Some(theirs)) => {
+                if before == item {
+                    *item = FinItem {
+                        selected: item.selected,
+                        ..theirs.clone()
+                    };
+                } else if before != theirs {
+                    merged.conflicts.push(conflict(Some(item), Some(theirs)));
+                }
+            }
+            (Some(before), None) => {
+                if before == item {
+                    merged.deleted.push(item.id);
+                } else {
+                    merged.conflicts.push(conflict(Some(item), None));
+                }
+            }
+        }
+    }
+
+    // added by them, or deleted by us
+    for theirs in theirs {
+        if ours.iter().any(|i| i.id == theirs.id) {
+            continue;
+        }
+        match base.get(&theirs.id) {
+            None => ours.push(theirs.clone()),
+            Some(before) if *before == theirs => {}
+            Some(_) => merged.conflicts.push(conflict(None, Some(theirs))),
+        }
+    }
+    merged
+}
+
+/// The device of a file in the sync folder, None for other files
+fn device_of(path: &Path) -> Option<String> {
+    if path.extension()? != ledger::EXTENSION {
+        return None;
+    }
+    Some(path.file_stem()?.to_string_lossy().into_owned())
+}
+
+impl TemplateApp {
+    /// Merge the files of the other devices that changed since the last
+    /// check, and write ours if the ledger changed
+    pub(crate) fn poll_sync(&mut self, ctx: &egui::Context) {
+        if self.sync.is_none() {
+            return;
+        }
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(POLL_INTERVAL));
+        let time = ctx.input().time;
+        if time - self.sync_time < POLL_INTERVAL {
+            return;
+        }
+        self.sync_time = time;
+        self.sync_now(false);
+    }
+
+    /// Merge the files of the other devices, all of them if forced
+    pub(crate) fn sync_now(&mut self, force: bool) {
+        let Some(mut sync) = self.sync.take() else {
+            return;
+        };
+        // our edits are ours, the merged ones are in the logs of the others
+        self.record_now();
+        self.commit_undo();
+
+        let entries = match std::fs::read_dir(&sync.folder) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to read {}: {}", sync.folder.display(), e);
+                self.sync = Some(sync);
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(device) = device_of(&path).filter(|d| *d != self.device) else {
+                continue;
+            };
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            if !force && modified.is_some() && sync.seen.get(&path) == modified.as_ref() {
+                continue;
+            }
+
+            let theirs = match std::fs::read(&path).map(|bytes| self.read_ledger(&bytes)) {
+                Ok(Ok(theirs)) => theirs,
+                Ok(Err(e)) => {
+                    warn!("Failed to merge {}: {}", path.display(), e);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to merge {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let base = sync.bases.remove(&device).unwrap_or_default();
+            let merged = merge(&base, &mut self.items, &theirs.items, &device);
+            info!(
+                "Merged {} with {} conflicts",
+                path.display(),
+                merged.conflicts.len()
+            );
+            for id in merged.deleted {
+                self.delete_item(id);
+            }
+            for conflict in merged.conflicts {
+                // the newer conflict of the item replaces the older
+                sync.conflicts.retain(|c| c.id() != conflict.id());
+                sync.conflicts.push(conflict);
+            }
+            for category in theirs.categories {
+                if !self.categories.contains(&category) {
+                    self.categories.push(category);
+                }
+            }
+            self.merge_history(theirs.history);
+            sync.bases.insert(device, theirs.items);
+            if let Some(modified) = modified {
+                sync.seen.insert(path, modified);
+            }
+        }
+
+        self.recorded = Some(self.items.clone());
+
+        let ledger = self.ledger();
+        if sync.written.as_ref() != Some(&ledger) {
+            let path = sync
+                .folder
+                .join(format!("{}.{}", self.device, ledger::EXTENSION));
+            let written = self.ledger_bytes(&ledger).and_then(|bytes| {
+                std::fs::write(&path, bytes).map_err(|e| ledger::LedgerError(e.to_string()))
+            });
+            match written {
+                Ok(()) => sync.written = Some(ledger),
+                Err(e) => warn!("Failed to write {}: {}", path.display(), e),
+            }
+        }
+        self.sync = Some(sync);
+    }
+
+    /// Keep our side of the conflict, or take theirs
+    pub(crate) fn resolve_conflict(&mut self, index: usize, take_theirs: bool) {
+        let Some(sync) = &mut self.sync else {
+            return;
+        };
+        if index >= sync.conflicts.len() {
+            return;
+        }
+        let conflict = sync.conflicts.remove(index);
+        if !take_theirs {
+            return;
+        }
+        let Some(id) = conflict.id() else {
+            return;
+        };
+        match (self.items.iter().position(|i| i.id == id), conflict.theirs) {
+            (Some(index), Some(theirs)) => self.items[index] = theirs,
+            (Some(_), None) => self.delete_item(id),
+            (None, Some(theirs)) => self.items.push(theirs),
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn item(name: &str, price: f32) -> FinItem {
+        FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        )
+    }
+
+    fn changed(item: &FinItem, price: f32) -> FinItem {
+        FinItem {
+            price,
+            ..item.clone()
+        }
+    }
+
+    #[test]
+    fn keeps_items_the_same_on_both_sides() {
+        let rent = item("Rent", 800.0);
+        let mut ours = vec![rent.clone()];
+        let merged = merge(
+            std::slice::from_ref(&rent),
+            &mut ours,
+            std::slice::from_ref(&rent),
+            "phone",
+        );
+        assert!(ours == [rent]);
+        assert!(merged.conflicts.is_empty());
+        assert!(merged.deleted.is_empty());
+    }
+
+    #[test]
+    fn keeps_items_they_have_not_seen() {
+        let rent = item("Rent", 800.0);
+        let mut ours = vec![rent.clone()];
+        let merged = merge(&[], &mut ours, &[], "phone");
+        assert!(ours == [rent]);
+        assert!(merged.conflicts.is_empty());
+        assert!(merged.deleted.is_empty());
+    }
+
+    #[test]
+    fn conflicts_on_items_added_differently_on_both_sides() {
+        let rent = item("Rent", 800.0);
+        let mut ours = vec![rent.clone()];
+        let merged = merge(&[], &mut ours, &[changed(&rent, 900.0)], "phone");
+        assert!(ours == [rent.clone()]);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert!(merged.conflicts[0].ours == Some(rent));
+        assert_eq!(merged.conflicts[0].device, "phone");
+    }
+
+    #[test]
+    fn takes_their_changes_and_keeps_ours() {
+        let rent = item("Rent", 800.0);
+        let power = item("Power", 60.0);
+        let base = [rent.clone(), power.clone()];
+        let mut ours = vec![rent.clone(), changed(&power, 70.0)];
+        let theirs = [changed(&rent, 900.0), power];
+        let merged = merge(&base, &mut ours, &theirs, "phone");
+        assert_eq!(ours[0].price, 900.0);
+        assert_eq!(ours[1].price, 70.0);
+        assert!(merged.conflicts.is_empty());
+
+        // changed on both sides, ours is kept
+        let mut ours = vec![changed(&rent, 850.0)];
+        let merged = merge(
+            std::slice::from_ref(&rent),
+            &mut ours,
+            &[changed(&rent, 900.0)],
+            "phone",
+        );
+        assert_eq!(ours[0].price, 850.0);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].theirs.as_ref().unwrap().price, 900.0);
+    }
+
+    #[test]
+    fn leaves_items_deleted_by_them_to_be_trashed() {
+        let rent = item("Rent", 800.0);
+        let mut ours = vec![rent.clone()];
+        let merged = merge(std::slice::from_ref(&rent), &mut ours, &[], "phone");
+        assert!(ours == [rent.clone()]);
+        assert_eq!(merged.deleted, [rent.id]);
+        assert!(merged.conflicts.is_empty());
+
+        // deleted by them and changed by us
+        let mut ours = vec![changed(&rent, 850.0)];
+        let merged = merge(std::slice::from_ref(&rent), &mut ours, &[], "phone");
+        assert_eq!(ours.len(), 1);
+        assert!(merged.deleted.is_empty());
+        assert!(merged.conflicts[0].theirs.is_none());
+    }
+
+    #[test]
+    fn adds_their_items_and_skips_ours_deleted() {
+        let rent = item("Rent", 800.0);
+        let power = item("Power", 60.0);
+        let mut ours = Vec::new();
+        let merged = merge(
+            std::slice::from_ref(&rent),
+            &mut ours,
+            &[rent.clone(), power.clone()],
+            "phone",
+        );
+        assert!(ours == [power]);
+        assert!(merged.conflicts.is_empty());
+
+        // deleted by us and changed by them
+        let mut ours = Vec::new();
+        let merged = merge(
+            std::slice::from_ref(&rent),
+            &mut ours,
+            &[changed(&rent, 900.0)],
+            "phone",
+        );
+        assert!(ours.is_empty());
+        assert!(merged.conflicts[0].ours.is_none());
+    }
+
+    #[test]
+    fn moves_items_deleted_by_them_to_the_trash() {
+        let folder = std::env::temp_dir().join(format!("fincal-sync-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let rent = item("Rent", 800.0);
+        let power = item("Power", 60.0);
+        let theirs = Ledger {
+            items: vec![power.clone()],
+            ..Ledger::default()
+        };
+        std::fs::write(
+            folder.join(format!("phone.{}", ledger::EXTENSION)),
+            theirs.to_bytes(),
+        )
+        .unwrap();
+
+        let mut app = TemplateApp::default();
+        app.device = "laptop".to_owned();
+        app.items = vec![rent.clone(), power.clone()];
+        let mut sync = SyncFolder::new(folder.clone());
+        sync.bases
+            .insert("phone".to_owned(), vec![rent.clone(), power.clone()]);
+        app.sync = Some(sync);
+        app.sync_now(true);
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        assert!(app.items == [power]);
+        assert_eq!(app.trash.len(), 1);
+        assert!(app.trash[0].item.id == rent.id);
+    }
+}
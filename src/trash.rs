@@ -0,0 +1,131 @@
+use chrono::NaiveDateTime;
+use log::info;
+use uuid::Uuid;
+
+use crate::app::TemplateApp;
+use crate::model::FinItem;
+
+/// Days deleted items are kept by default
+pub(crate) const DEFAULT_TRASH_DAYS: u32 = 30;
+
+/// A deleted item, kept in the trash of the ledger to be restored
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq)]
+pub struct Trashed {
+    pub(crate) item: FinItem,
+    pub(crate) deleted: NaiveDateTime,
+}
+
+impl TemplateApp {
+    /// Delete the item, or ask first if deleting needs a confirmation
+    pub(crate) fn request_delete(&mut self, id: Uuid) {
+        if self.confirm_delete {
+            self.pending_delete = Some(id);
+        } else {
+            self.delete_item(id);
+        }
+    }
+
+    /// Move the item to the trash
+    pub(crate) fn delete_item(&mut self, id: Uuid) {
+        let Some(index) = self.items.iter().position(|i| i.id == id) else {
+            return;
+        };
+        let mut item = self.items.remove(index);
+        item.selected = false;
+        item.editable = false;
+        self.trash.push(Trashed {
+            item,
+            deleted: chrono::offset::Local::now().naive_local(),
+        });
+        self.purge_old_trash();
+    }
+
+    /// Put the item back into the ledger
+    pub(crate) fn restore_trashed(&mut self, index: usize) {
+        if index >= self.trash.len() {
+            return;
+        }
+        let trashed = self.trash.remove(index);
+        // e.g. deleted again after restoring a backup
+        if !self.items.iter().any(|i| i.id == trashed.item.id) {
+            self.items.push(trashed.item);
+        }
+    }
+
+    /// Delete the items in the trash that are older than the configured age
+    pub(crate) fn purge_old_trash(&mut self) {
+        if self.trash_days == 0 {
+            return;
+        }
+        let now = chrono::offset::Local::now().naive_local();
+        let count = self.trash.len();
+        self.trash
+            .retain(|t| (now - t.deleted).num_days() < i64::from(self.trash_days));
+        if self.trash.len() != count {
+            info!("Purged {} items from the trash", count - self.trash.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+
+    fn trashed(name: &str, days_ago: i64) -> Trashed {
+        Trashed {
+            item: FinItem::new(
+                NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+                name.to_owned(),
+                1.0,
+            ),
+            deleted: chrono::offset::Local::now().naive_local() - Duration::days(days_ago),
+        }
+    }
+
+    fn app() -> TemplateApp {
+        let mut app = TemplateApp::default();
+        app.trash = vec![trashed("Old", 40), trashed("Recent", 2), trashed("Now", 0)];
+        app
+    }
+
+    #[test]
+    fn purges_items_older_than_the_days() {
+        let mut app = app();
+        app.trash_days = DEFAULT_TRASH_DAYS;
+        app.purge_old_trash();
+        assert_eq!(app.trash.len(), 2);
+        assert_eq!(app.trash[0].item.item, "Recent");
+
+        app.trash_days = 1;
+        app.purge_old_trash();
+        assert_eq!(app.trash.len(), 1);
+        assert_eq!(app.trash[0].item.item, "Now");
+    }
+
+    #[test]
+    fn keeps_all_items_without_days() {
+        let mut app = app();
+        app.trash_days = 0;
+        app.purge_old_trash();
+        assert_eq!(app.trash.len(), 3);
+    }
+
+    #[test]
+    fn deletes_and_restores_items() {
+        let mut app = TemplateApp::default();
+        let mut rent = trashed("Rent", 0).item;
+        rent.selected = true;
+        let id = rent.id;
+        app.items = vec![rent];
+
+        app.delete_item(id);
+        assert!(app.items.is_empty());
+        assert!(!app.trash[0].item.selected);
+
+        app.restore_trashed(0);
+        assert!(app.trash.is_empty());
+        assert_eq!(app.items[0].id, id);
+    }
+}
@@ -0,0 +1,239 @@
+use egui::{Key, KeyboardShortcut, Modifiers};
+use log::info;
+
+use crate::app::TemplateApp;
+use crate::model::FinItem;
+use crate::trash::Trashed;
+
+/// Steps kept to undo, the oldest ones are dropped
+const UNDO_LIMIT: usize = 100;
+
+pub(crate) const UNDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+pub(crate) const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+/// The items and the trash before each change of the shown ledger. A change
+/// is complete when the pointer is released and no field is focused, so
+/// dragging a slider or typing into a field is one step.
+#[derive(Default)]
+pub(crate) struct UndoStack {
+    /// The state after the last complete change
+    base: Option<Snapshot>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+/// Deleted items are in the trash, undoing takes them out again
+#[derive(Clone, PartialEq)]
+struct Snapshot {
+    items: Vec<FinItem>,
+    trash: Vec<Trashed>,
+}
+
+impl TemplateApp {
+    /// Undo or redo with the keyboard, unless a field handles it itself
+    pub(crate) fn undo_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.memory().focus().is_some() {
+            return;
+        }
+        if ctx.input_mut().consume_shortcut(&REDO_SHORTCUT) {
+            self.redo();
+        } else if ctx.input_mut().consume_shortcut(&UNDO_SHORTCUT) {
+            self.undo();
+        }
+    }
+
+    /// Keep the items before a change that is complete
+    pub(crate) fn track_undo(&mut self, ctx: &egui::Context) {
+        let editing = ctx.input().pointer.any_down() || ctx.memory().focus().is_some();
+        if !editing {
+            self.commit_undo();
+        }
+    }
+
+    /// Make the changes since the last step one step
+    pub(crate) fn commit_undo(&mut self) {
+        // runs every frame, so compare before copying the items
+        if self.undo.base.is_some() && !self.has_uncommitted_change() {
+            return;
+        }
+        let snapshot = self.snapshot();
+        let stack = &mut self.undo;
+        let Some(base) = &mut stack.base else {
+            stack.base = Some(snapshot);
+            return;
+        };
+        let before = std::mem::replace(base, snapshot);
+        stack.undo.push(before);
+        if stack.undo.len() > UNDO_LIMIT {
+            stack.undo.remove(0);
+        }
+        stack.redo.clear();
+    }
+
+    /// Forget the steps, e.g. of another ledger
+    pub(crate) fn clear_undo(&mut self) {
+        self.undo = UndoStack::default();
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.undo.undo.is_empty() || self.has_uncommitted_change()
+    }
+
+    /// Whether the items or the trash changed since the last step
+    fn has_uncommitted_change(&self) -> bool {
+        self.undo
+            .base
+            .as_ref()
+            .map_or(false, |b| b.items != self.items || b.trash != self.trash)
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.undo.redo.is_empty()
+    }
+
+    pub(crate) fn undo(&mut self) {
+        self.commit_undo();
+        let Some(before) = self.undo.undo.pop() else {
+            return;
+        };
+        let after = self.restore_snapshot(before);
+        self.undo.redo.push(after);
+        info!("Undid a change, {} more to undo", self.undo.undo.len());
+    }
+
+    pub(crate) fn redo(&mut self) {
+        self.commit_undo();
+        let Some(after) = self.undo.redo.pop() else {
+            return;
+        };
+        let before = self.restore_snapshot(after);
+        self.undo.undo.push(before);
+        info!("Redid a change, {} more to redo", self.undo.redo.len());
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            items: self.items.clone(),
+            trash: self.trash.clone(),
+        }
+    }
+
+    /// Go back or forward to the snapshot, returns the replaced one
+    fn restore_snapshot(&mut self, snapshot: Snapshot) -> Snapshot {
+        let replaced = Snapshot {
+            items: std::mem::replace(&mut self.items, snapshot.items.clone()),
+            trash: std::mem::replace(&mut self.trash, snapshot.trash.clone()),
+        };
+        self.undo.base = Some(snapshot);
+        replaced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn item(name: &str, price: f32) -> FinItem {
+        FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        )
+    }
+
+    fn app() -> TemplateApp {
+        let mut app = TemplateApp::default();
+        app.items = vec![item("Rent", 800.0)];
+        app.commit_undo();
+        app
+    }
+
+    #[test]
+    fn undoes_and_redoes_changes() {
+        let mut app = app();
+        assert!(!app.can_undo());
+        app.items[0].price = 900.0;
+        app.commit_undo();
+        app.items.push(item("Power", 60.0));
+        app.commit_undo();
+
+        app.undo();
+        assert_eq!(app.items.len(), 1);
+        assert_eq!(app.items[0].price, 900.0);
+        app.undo();
+        assert_eq!(app.items[0].price, 800.0);
+        assert!(!app.can_undo());
+        app.undo();
+        assert_eq!(app.items[0].price, 800.0);
+
+        app.redo();
+        app.redo();
+        assert_eq!(app.items.len(), 2);
+        assert!(!app.can_redo());
+    }
+
+    #[test]
+    fn undoes_changes_not_committed_yet() {
+        let mut app = app();
+        app.items[0].price = 900.0;
+        assert!(app.can_undo());
+        app.undo();
+        assert_eq!(app.items[0].price, 800.0);
+        app.redo();
+        assert_eq!(app.items[0].price, 900.0);
+    }
+
+    #[test]
+    fn forgets_the_redo_steps_with_a_new_change() {
+        let mut app = app();
+        app.items[0].price = 900.0;
+        app.undo();
+        assert!(app.can_redo());
+        app.items[0].item = "Flat".to_owned();
+        app.commit_undo();
+        assert!(!app.can_redo());
+    }
+
+    #[test]
+    fn takes_deleted_items_out_of_the_trash() {
+        let mut app = app();
+        let id = app.items[0].id;
+        app.delete_item(id);
+        app.commit_undo();
+        assert_eq!(app.trash.len(), 1);
+
+        app.undo();
+        assert_eq!(app.items[0].id, id);
+        assert!(app.trash.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_latest_steps() {
+        let mut app = app();
+        for price in 0..UNDO_LIMIT + 10 {
+            app.items[0].price = price as f32;
+            app.commit_undo();
+        }
+        let mut steps = 0;
+        while app.can_undo() {
+            app.undo();
+            steps += 1;
+        }
+        assert_eq!(steps, UNDO_LIMIT);
+        assert_eq!(app.items[0].price, 9.0);
+    }
+
+    #[test]
+    fn forgets_the_steps_of_another_ledger() {
+        let mut app = app();
+        app.items[0].price = 900.0;
+        app.commit_undo();
+        app.set_ledger(crate::ledger::Ledger::default());
+        assert!(!app.can_undo());
+        assert!(!app.can_redo());
+    }
+}
@@ -1,4 +1,15 @@
+pub(crate) mod backups_view;
 pub(crate) mod bottom_panel_view;
 pub(crate) mod central_panel_view;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod conflicts_view;
+pub(crate) mod history_view;
+pub(crate) mod import_preview_view;
+pub(crate) mod passphrase_view;
+pub(crate) mod rules_view;
 pub(crate) mod side_panel_view;
+pub(crate) mod state_error_view;
 pub(crate) mod top_panel_view;
+pub(crate) mod trash_view;
+pub(crate) mod unlock_view;
+pub(crate) mod unsaved_changes_view;
@@ -0,0 +1,37 @@
+use crate::{recovery::BackupKind, TemplateApp};
+
+/// Window listing the rotating backups of the state, to restore one
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    let mut open = app.show_backups;
+    egui::Window::new("Restore Backup")
+        .open(&mut open)
+        .vscroll(true)
+        .show(ctx, |ui| {
+            ui.label("Restoring replaces all ledgers, the current ones are kept as backup.");
+            if app.backups.is_empty() {
+                ui.label("No backups yet, they are made when the app saves its state.");
+            }
+
+            for kind in BackupKind::ALL {
+                let backups: Vec<_> = app.backups.iter().filter(|b| b.kind == kind).collect();
+                if backups.is_empty() {
+                    continue;
+                }
+                ui.separator();
+                ui.strong(kind.to_string());
+                egui::Grid::new(kind.to_string())
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for backup in backups {
+                            ui.label(backup.time.format("%Y-%m-%d %H:%M").to_string());
+                            ui.label(format!("{} items", backup.items));
+                            if ui.button("Restore").clicked() {
+                                app.pending_restore = Some(backup.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+            }
+        });
+    app.show_backups = open && app.pending_restore.is_none();
+}
@@ -1,8 +1,13 @@
 use egui::plot::{Bar, BarChart, HLine, Legend, Line, Plot, PlotPoints};
 
-use crate::{common::to_name, model::FinItem, TemplateApp};
+use log::warn;
 
-pub(crate) fn show(ui: &mut egui::Ui, app: &mut TemplateApp, items_in_month: &Vec<FinItem>) {
+use crate::{common::to_name, files::save_file, model::FinItem, report::Report, TemplateApp};
+
+/// Pixels per point of exported png charts
+const PNG_SCALE: f32 = 2.0;
+
+pub(crate) fn show(ui: &mut egui::Ui, app: &mut TemplateApp, items_in_month: &[FinItem]) {
     let mut bars: Vec<Bar> = Vec::new();
     let mut dots: Vec<f64> = Vec::new();
 
@@ -46,5 +51,25 @@ pub(crate) fn show(ui: &mut egui::Ui, app: &mut TemplateApp, items_in_month: &Ve
         plot_ui.line(line);
 
         plot_ui.hline(hline);
-    });
+    })
+    .response
+    .context_menu(|ui| chart_export_buttons(ui, app));
+}
+
+/// Save the chart of the selected month as an image
+pub(crate) fn chart_export_buttons(ui: &mut egui::Ui, app: &TemplateApp) {
+    if ui.button("Save chart as SVG").clicked() {
+        let chart = Report::new(&app.items, app.selected_year, app.selected_month).chart_image();
+        save_file("svg", "svg", chart.svg());
+        ui.close_menu();
+    }
+
+    if ui.button("Save chart as PNG").clicked() {
+        let chart = Report::new(&app.items, app.selected_year, app.selected_month).chart_image();
+        match chart.png(PNG_SCALE) {
+            Ok(png) => save_file("png", "png", png),
+            Err(e) => warn!("Failed to render chart {}", e),
+        }
+        ui.close_menu();
+    }
 }
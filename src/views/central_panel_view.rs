@@ -1,6 +1,11 @@
 use egui_extras::Column;
 
-use crate::{common::to_name, model::FinItem, TemplateApp};
+use crate::{
+    common::to_name,
+    formats,
+    model::{FinItem, Recurrence},
+    shorthand, TemplateApp,
+};
 
 pub(crate) fn show(
     ui: &mut egui::Ui,
@@ -43,35 +48,77 @@ pub(crate) fn show(
 
     // main panel
     egui::ScrollArea::vertical().show(ui, |ui| {
-        // Add item button
-        if ui.button("Add Item").clicked() {
-            app.items.push(FinItem {
-                date: chrono::offset::Local::now().date_naive(),
-                item: "item".to_string(),
-                category: Some("category".to_string()),
-                price: 0.0,
-                owner: "MB".to_string(),
-                ratio: 0.5,
-                editable: false,
+        ui.horizontal(|ui| {
+            // Add item button
+            if ui.button("Add Item").clicked() {
+                app.add_item(FinItem::new(
+                    chrono::offset::Local::now().date_naive(),
+                    "item".to_string(),
+                    0.0,
+                ));
+            }
+
+            // selected rows as tsv for spreadsheets
+            if ui
+                .button("Copy selected")
+                .on_hover_text("Ctrl+C, paste spreadsheet rows with Ctrl+V")
+                .clicked()
+            {
+                if let Some(text) = app.copy_selected() {
+                    ui.output().copied_text = text;
+                }
+            }
+
+            if ui.button("Apply rules to selected").clicked() {
+                app.apply_rules_to_selected();
+            }
+
+            // to the other ledgers
+            ui.add_enabled_ui(!app.workspaces.is_empty(), |ui| {
+                let names: Vec<String> = app.workspaces.iter().map(|w| w.name.clone()).collect();
+                ui.menu_button("Move selected to", |ui| {
+                    for name in &names {
+                        if ui.button(name).clicked() {
+                            app.move_selected_to(name);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("Copy selected to", |ui| {
+                    for name in &names {
+                        if ui.button(name).clicked() {
+                            app.copy_selected_to(name);
+                            ui.close_menu();
+                        }
+                    }
+                });
             });
-        }
+        });
+
+        quick_add(ui, app);
+        favorites(ui, app);
 
         // main grid
 
-        let mut to_remove: Option<&FinItem> = None;
+        let mut to_remove = None;
+        let mut to_favorite: Option<FinItem> = None;
+        let mut to_history = None;
         egui_extras::TableBuilder::new(ui)
             .striped(true)
             .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
             //.column(Column::auto().at_least(40.0).resizable(true).clip(true)) // date
+            .column(Column::auto()) // selection
             .column(Column::auto()) // date
             .column(Column::auto()) // item
             .column(Column::auto()) // category
             .column(Column::auto()) // price
             .column(Column::auto()) // name
             .column(Column::auto()) // ratio
+            .column(Column::auto()) // recurrence
             .column(Column::auto()) // Total
             .column(Column::remainder()) // Options
             .header(20.0, |mut header| {
+                header.col(|_| {});
                 header.col(|ui| {
                     ui.strong("Date");
                 });
@@ -90,6 +137,9 @@ pub(crate) fn show(
                 header.col(|ui| {
                     ui.strong("Ratio");
                 });
+                header.col(|ui| {
+                    ui.strong("Repeats");
+                });
                 header.col(|ui| {
                     ui.strong("Total");
                 });
@@ -100,6 +150,10 @@ pub(crate) fn show(
             .body(|mut body| {
                 for row in app.items.iter_mut().filter(|i| items_in_month.contains(i)) {
                     body.row(18.0, |mut table_row| {
+                        table_row.col(|ui| {
+                            ui.checkbox(&mut row.selected, "");
+                        });
+
                         // editable fields
                         if row.editable {
                             table_row.col(|ui| {
@@ -146,24 +200,44 @@ pub(crate) fn show(
                             table_row.col(|ui| {
                                 ui.add(egui::Slider::new(&mut row.ratio, 0.0..=1.0));
                             });
+                            table_row.col(|ui| {
+                                egui::ComboBox::from_id_source("Recurrence")
+                                    .selected_text(recurrence_text(row.recurrence))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut row.recurrence, None, "Never");
+                                        for r in Recurrence::ALL {
+                                            ui.selectable_value(
+                                                &mut row.recurrence,
+                                                Some(r),
+                                                r.to_string(),
+                                            );
+                                        }
+                                    });
+                            });
                         } else {
                             table_row.col(|ui| {
-                                ui.label(&row.date.to_string());
+                                ui.label(row.date.to_string());
                             });
                             table_row.col(|ui| {
-                                ui.label(&row.item);
+                                let label = ui.label(&row.item);
+                                if !row.tags.is_empty() {
+                                    label.on_hover_text(row.tags.join(", "));
+                                }
                             });
                             table_row.col(|ui| {
                                 ui.label(row.category.as_ref().unwrap_or(&"None".to_string()));
                             });
                             table_row.col(|ui| {
-                                ui.label(&row.price.to_string());
+                                ui.label(row.price.to_string());
                             });
                             table_row.col(|ui| {
                                 ui.label(&row.owner);
                             });
                             table_row.col(|ui| {
-                                ui.label(&row.ratio.to_string());
+                                ui.label(row.ratio.to_string());
+                            });
+                            table_row.col(|ui| {
+                                ui.label(recurrence_text(row.recurrence));
                             });
                         }
 
@@ -181,8 +255,23 @@ pub(crate) fn show(
                             }
 
                             if ui.add(egui::Button::new("Delete")).clicked() {
-                                // get current index
-                                _ = to_remove.insert(row);
+                                to_remove = Some(row.id);
+                            }
+
+                            if ui
+                                .add(egui::Button::new("☆"))
+                                .on_hover_text("Save as favorite")
+                                .clicked()
+                            {
+                                to_favorite = Some(row.clone());
+                            }
+
+                            if ui
+                                .add(egui::Button::new("🕓"))
+                                .on_hover_text("Show history")
+                                .clicked()
+                            {
+                                to_history = Some(row.id);
                             }
                         });
                     });
@@ -190,11 +279,110 @@ pub(crate) fn show(
             });
 
         // handle delete
-        if let Some(c) = to_remove {
-            let cc = c.to_owned();
-            if let Some(pos) = app.items.iter().position(|x| *x == cc) {
-                app.items.remove(pos);
+        if let Some(id) = to_remove {
+            app.request_delete(id);
+        }
+
+        if let Some(item) = to_favorite {
+            app.add_template(&item);
+        }
+
+        if to_history.is_some() {
+            app.history_item = to_history;
+        }
+    });
+}
+
+/// One button per template, right click to remove
+fn favorites(ui: &mut egui::Ui, app: &mut TemplateApp) {
+    if app.templates.is_empty() {
+        return;
+    }
+
+    let mut to_insert = None;
+    let mut to_remove = None;
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Favorites:");
+        for (i, template) in app.templates.iter().enumerate() {
+            let button = ui
+                .button(format!("{} {:.2}", template.item, template.price))
+                .on_hover_text(format!(
+                    "{} | {} | {}",
+                    template.category.as_deref().unwrap_or("None"),
+                    template.owner,
+                    template.ratio
+                ));
+            if button.clicked() {
+                to_insert = Some(i);
             }
+            button.context_menu(|ui| {
+                if ui.button("Remove").clicked() {
+                    to_remove = Some(i);
+                    ui.close_menu();
+                }
+            });
         }
     });
+
+    if let Some(i) = to_insert {
+        let template = app.templates[i].clone();
+        app.insert_template(&template);
+    }
+    if let Some(i) = to_remove {
+        app.templates.remove(i);
+    }
+}
+
+/// Single line input that creates a complete item, with preview and completions
+fn quick_add(ui: &mut egui::Ui, app: &mut TemplateApp) {
+    let today = chrono::offset::Local::now().date_naive();
+    // with the rules applied, so the preview shows what is saved
+    let parsed = shorthand::parse(&app.quick_add, today, &app.categories, &app.rules);
+
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut app.quick_add)
+                .hint_text("12.50 groceries Rewe @anna 50% yesterday")
+                .desired_width(300.0),
+        );
+        if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+            if let Some(item) = parsed.clone() {
+                app.items.push(item);
+                app.quick_add.clear();
+            }
+            response.request_focus();
+        }
+
+        // live preview
+        match &parsed {
+            Some(item) => ui.weak(format!(
+                "{} | {} | {} | {:.2} | {} | {}",
+                item.date,
+                item.item,
+                item.category.as_deref().unwrap_or("None"),
+                item.price,
+                item.owner,
+                item.ratio
+            )),
+            None if app.quick_add.is_empty() => ui.weak("Quick add, Enter to save"),
+            None => ui.weak("Needs a price"),
+        };
+    });
+
+    let owners = formats::people(&app.items);
+    let suggestions = shorthand::suggestions(&app.quick_add, &app.categories, &owners);
+    if !suggestions.is_empty() {
+        ui.horizontal(|ui| {
+            for suggestion in suggestions {
+                let word = suggestion.split_whitespace().last().unwrap_or_default();
+                if ui.small_button(word).clicked() {
+                    app.quick_add = suggestion;
+                }
+            }
+        });
+    }
+}
+
+fn recurrence_text(recurrence: Option<Recurrence>) -> String {
+    recurrence.map_or("Never".to_owned(), |r| r.to_string())
 }
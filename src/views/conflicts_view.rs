@@ -0,0 +1,60 @@
+use crate::{model::FinItem, TemplateApp};
+
+/// Window to resolve the items changed on this and another device
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    let Some(sync) = &app.sync else {
+        return;
+    };
+
+    let mut open = app.show_conflicts;
+    let mut resolved = None;
+    egui::Window::new("Sync Conflicts")
+        .open(&mut open)
+        .vscroll(true)
+        .show(ctx, |ui| {
+            if sync.conflicts.is_empty() {
+                ui.label("No conflicts, every change was merged.");
+            }
+
+            egui::Grid::new("conflicts").striped(true).show(ui, |ui| {
+                ui.strong("Mine");
+                ui.strong("Theirs");
+                ui.end_row();
+
+                for (i, conflict) in sync.conflicts.iter().enumerate() {
+                    ui.label(describe(conflict.ours.as_ref()));
+                    ui.label(format!(
+                        "{} on {}",
+                        describe(conflict.theirs.as_ref()),
+                        conflict.device
+                    ));
+                    if ui.button("Keep mine").clicked() {
+                        resolved = Some((i, false));
+                    }
+                    if ui.button("Take theirs").clicked() {
+                        resolved = Some((i, true));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    if let Some((index, take_theirs)) = resolved {
+        app.resolve_conflict(index, take_theirs);
+    }
+    app.show_conflicts = open;
+}
+
+fn describe(item: Option<&FinItem>) -> String {
+    match item {
+        Some(item) => format!(
+            "{} {} {:.2} {} ({})",
+            item.date,
+            item.item,
+            item.price,
+            item.category.as_deref().unwrap_or("-"),
+            item.owner
+        ),
+        None => "Deleted".to_owned(),
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::NaiveDate;
+
+use crate::{history::Change, TemplateApp};
+
+/// What the activity log shows, everything by default
+#[derive(Default)]
+pub(crate) struct ActivityFilter {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    user: Option<String>,
+}
+
+impl ActivityFilter {
+    fn matches(&self, change: &Change) -> bool {
+        let date = change.time.date();
+        self.from.map_or(true, |from| date >= from)
+            && self.to.map_or(true, |to| date <= to)
+            && self.user.as_ref().map_or(true, |user| change.user == *user)
+    }
+}
+
+/// The activity log of the ledger, and the history of a single item
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    activity(ctx, app);
+    item_history(ctx, app);
+}
+
+fn activity(ctx: &egui::Context, app: &mut TemplateApp) {
+    let mut open = app.show_activity;
+    let mut to_show = None;
+    egui::Window::new("Activity")
+        .open(&mut open)
+        .vscroll(true)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Changes are recorded as");
+                ui.text_edit_singleline(&mut app.user);
+            });
+
+            let filter = &mut app.activity_filter;
+            ui.horizontal(|ui| {
+                date_bound(ui, "From", &mut filter.from);
+                date_bound(ui, "To", &mut filter.to);
+
+                let mut users: Vec<&str> = app.history.iter().map(|c| c.user.as_str()).collect();
+                users.sort_unstable();
+                users.dedup();
+                egui::ComboBox::from_label("Person")
+                    .selected_text(filter.user.as_deref().unwrap_or("Everyone"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut filter.user, None, "Everyone");
+                        for user in users {
+                            ui.selectable_value(&mut filter.user, Some(user.to_owned()), user);
+                        }
+                    });
+            });
+            ui.separator();
+
+            let changes: Vec<&Change> = app
+                .history
+                .iter()
+                .rev()
+                .filter(|c| filter.matches(c))
+                .collect();
+            if changes.is_empty() {
+                ui.label("No changes.");
+            }
+            egui::Grid::new("activity").striped(true).show(ui, |ui| {
+                for change in changes {
+                    ui.label(change.time.format("%Y-%m-%d %H:%M").to_string());
+                    ui.label(&change.user);
+                    ui.label(change.kind());
+                    if ui
+                        .link(change.item_name())
+                        .on_hover_text("Show the history of the item")
+                        .clicked()
+                    {
+                        to_show = Some(change.item);
+                    }
+                    ui.label(change.describe());
+                    ui.end_row();
+                }
+            });
+        });
+
+    if to_show.is_some() {
+        app.history_item = to_show;
+    }
+    app.show_activity = open;
+}
+
+fn item_history(ctx: &egui::Context, app: &mut TemplateApp) {
+    let Some(item) = app.history_item else {
+        return;
+    };
+    let changes: Vec<&Change> = app.history.iter().filter(|c| c.item == item).collect();
+    let name = changes.last().map_or("", |c| c.item_name());
+
+    let mut open = true;
+    egui::Window::new(format!("History of {name}"))
+        .id(egui::Id::new("item_history"))
+        .open(&mut open)
+        .vscroll(true)
+        .show(ctx, |ui| {
+            if changes.is_empty() {
+                ui.label("No changes were recorded for this item.");
+            }
+            egui::Grid::new("item_history")
+                .striped(true)
+                .show(ui, |ui| {
+                    for change in changes.iter().rev() {
+                        ui.label(change.time.format("%Y-%m-%d %H:%M").to_string());
+                        ui.label(&change.user);
+                        ui.label(change.kind());
+                        ui.label(change.describe());
+                        ui.end_row();
+                    }
+                });
+        });
+    if !open {
+        app.history_item = None;
+    }
+}
+
+/// A date the log is limited to, if checked
+fn date_bound(ui: &mut egui::Ui, label: &str, date: &mut Option<NaiveDate>) {
+    let mut limited = date.is_some();
+    if ui.checkbox(&mut limited, label).changed() {
+        *date = limited.then(|| chrono::offset::Local::now().date_naive());
+    }
+    if let Some(date) = date {
+        ui.add(egui_extras::DatePickerButton::new(date).id_source(label));
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::{model::FinItem, TemplateApp};
+
+/// Confirm dialog listing the parsed items before they are added
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    let Some(pending) = &app.pending_import else {
+        return;
+    };
+
+    // items or bank ids that are already present are skipped on import
+    let known_ids: HashSet<Uuid> = app.items.iter().map(|i| i.id).collect();
+    let known_fitids: HashSet<&str> = app
+        .items
+        .iter()
+        .filter_map(|i| i.fitid.as_deref())
+        .collect();
+    let is_duplicate = |item: &FinItem| {
+        known_ids.contains(&item.id)
+            || item
+                .fitid
+                .as_deref()
+                .map_or(false, |f| known_fitids.contains(f))
+    };
+    let duplicates = pending.items.iter().filter(|i| is_duplicate(i)).count();
+
+    let mut accept = false;
+    let mut cancel = false;
+    egui::Window::new("Import preview")
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.label(&pending.name);
+            ui.label(format!(
+                "{} items, {} already imported",
+                pending.items.len(),
+                duplicates
+            ));
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("import_preview")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for heading in
+                                ["Date", "Item", "Category", "Price", "Name", "Ratio", ""]
+                            {
+                                ui.strong(heading);
+                            }
+                            ui.end_row();
+
+                            for item in &pending.items {
+                                ui.label(item.date.to_string());
+                                ui.label(&item.item);
+                                ui.label(item.category.as_deref().unwrap_or("None"));
+                                ui.label(format!("{:.2}", item.price));
+                                ui.label(&item.owner);
+                                ui.label(item.ratio.to_string());
+                                if is_duplicate(item) {
+                                    ui.weak("duplicate");
+                                } else {
+                                    ui.label("");
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                accept = ui.button("Import").clicked();
+                cancel = ui.button("Cancel").clicked();
+            });
+        });
+
+    if accept {
+        app.accept_import();
+    } else if cancel {
+        app.pending_import = None;
+    }
+}
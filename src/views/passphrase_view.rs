@@ -0,0 +1,87 @@
+use zeroize::Zeroize;
+
+use crate::TemplateApp;
+
+/// Passphrases typed into the unlock and passphrase windows
+#[derive(Default)]
+pub(crate) struct PassphraseInput {
+    pub(crate) current: String,
+    pub(crate) new: String,
+    pub(crate) confirm: String,
+}
+
+impl Drop for PassphraseInput {
+    fn drop(&mut self) {
+        self.current.zeroize();
+        self.new.zeroize();
+        self.confirm.zeroize();
+    }
+}
+
+/// Window to set, change or remove the passphrase
+pub(crate) fn show(ctx: &egui::Context, frame: &mut eframe::Frame, app: &mut TemplateApp) {
+    let mut open = app.show_passphrase;
+    // Some(None) removes the passphrase
+    let mut change: Option<Option<String>> = None;
+    egui::Window::new("Passphrase")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label("The saved data and ledger files are encrypted with the passphrase.");
+            ui.label("Ledger files are encrypted when they are saved next.");
+            ui.label("SQLite documents are not saved with a passphrase, exports stay unencrypted.");
+            ui.separator();
+
+            let input = &mut app.passphrase_input;
+            let encrypted = app.cipher.is_some();
+            egui::Grid::new("passphrase").show(ui, |ui| {
+                if encrypted {
+                    ui.label("Current");
+                    ui.add(egui::TextEdit::singleline(&mut input.current).password(true));
+                    ui.end_row();
+                }
+                ui.label("New");
+                ui.add(egui::TextEdit::singleline(&mut input.new).password(true));
+                ui.end_row();
+                ui.label("Confirm");
+                ui.add(egui::TextEdit::singleline(&mut input.confirm).password(true));
+                ui.end_row();
+            });
+
+            if let Some(error) = &app.passphrase_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+
+            let current = app
+                .cipher
+                .as_ref()
+                .map_or(true, |c| c.matches(&input.current));
+            ui.horizontal(|ui| {
+                let label = if encrypted { "Change" } else { "Encrypt" };
+                if ui.button(label).clicked() {
+                    change = Some(Some(input.new.clone()));
+                }
+                if encrypted && ui.button("Remove encryption").clicked() {
+                    change = Some(None);
+                }
+            });
+
+            let error = match &change {
+                Some(_) if !current => Some("The current passphrase is wrong"),
+                Some(Some(new)) if new.is_empty() => Some("The passphrase is empty"),
+                Some(Some(new)) if *new != input.confirm => Some("The passphrases differ"),
+                _ => None,
+            };
+            if let Some(error) = error {
+                app.passphrase_error = Some(error.to_owned());
+                change = None;
+            }
+        });
+
+    if let (Some(passphrase), Some(storage)) = (change, frame.storage_mut()) {
+        app.set_passphrase(storage, passphrase.as_deref());
+        app.passphrase_input = Default::default();
+        open = app.passphrase_error.is_some();
+    }
+    app.show_passphrase = open;
+}
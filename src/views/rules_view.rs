@@ -0,0 +1,145 @@
+use crate::{
+    rules::{MatchKind, Rule},
+    TemplateApp,
+};
+
+/// Window to add, edit, order and delete the categorisation rules
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    let mut open = app.show_rules;
+    let mut apply = false;
+    egui::Window::new("Rules")
+        .open(&mut open)
+        .vscroll(true)
+        .show(ctx, |ui| {
+            ui.label("Rules run in order on imported and new items, later rules win.");
+            ui.horizontal(|ui| {
+                if ui.button("Add rule").clicked() {
+                    app.rules.push(Rule::default());
+                }
+                apply = ui.button("Apply to selected").clicked();
+            });
+            ui.separator();
+
+            let mut to_remove = None;
+            let mut to_raise = None;
+            let count = app.rules.len();
+            for (i, rule) in app.rules.iter_mut().enumerate() {
+                ui.push_id(i, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut rule.enabled, "");
+                        ui.text_edit_singleline(&mut rule.name);
+                        if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                            to_raise = Some(i);
+                        }
+                        if ui
+                            .add_enabled(i + 1 < count, egui::Button::new("⏷"))
+                            .clicked()
+                        {
+                            to_raise = Some(i + 1);
+                        }
+                        if ui.button("Delete").clicked() {
+                            to_remove = Some(i);
+                        }
+                    });
+                    rule_editor(ui, rule, &app.categories);
+                });
+                ui.separator();
+            }
+
+            if let Some(i) = to_raise {
+                app.rules.swap(i - 1, i);
+            }
+            if let Some(i) = to_remove {
+                app.rules.remove(i);
+            }
+        });
+    app.show_rules = open;
+
+    if apply {
+        app.apply_rules_to_selected();
+    }
+}
+
+fn rule_editor(ui: &mut egui::Ui, rule: &mut Rule, categories: &[String]) {
+    egui::Grid::new("rule").num_columns(2).show(ui, |ui| {
+        ui.label("Item or memo");
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("match_kind")
+                .selected_text(rule.match_kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in MatchKind::ALL {
+                        ui.selectable_value(&mut rule.match_kind, kind, kind.to_string());
+                    }
+                });
+            ui.text_edit_singleline(&mut rule.pattern);
+            if let Err(e) = rule.regex() {
+                ui.colored_label(egui::Color32::RED, "Invalid")
+                    .on_hover_text(e.to_string());
+            }
+        });
+        ui.end_row();
+
+        ui.label("Amount");
+        ui.horizontal(|ui| {
+            optional(ui, "from", &mut rule.min_amount, 0.0, |ui, v| {
+                ui.add(egui::DragValue::new(v).speed(0.1));
+            });
+            optional(ui, "to", &mut rule.max_amount, 100.0, |ui, v| {
+                ui.add(egui::DragValue::new(v).speed(0.1));
+            });
+        });
+        ui.end_row();
+
+        ui.label("Account contains");
+        ui.text_edit_singleline(&mut rule.account);
+        ui.end_row();
+
+        ui.label("Set category");
+        egui::ComboBox::from_id_source("category")
+            .selected_text(rule.category.as_deref().unwrap_or("Keep"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.category, None, "Keep");
+                for c in categories {
+                    ui.selectable_value(&mut rule.category, Some(c.clone()), c);
+                }
+            });
+        ui.end_row();
+
+        ui.label("Set name");
+        ui.horizontal(|ui| {
+            optional(ui, "", &mut rule.owner, String::new(), |ui, v| {
+                ui.text_edit_singleline(v);
+            });
+        });
+        ui.end_row();
+
+        ui.label("Set ratio");
+        ui.horizontal(|ui| {
+            optional(ui, "", &mut rule.ratio, 0.5, |ui, v| {
+                ui.add(egui::Slider::new(v, 0.0..=1.0));
+            });
+        });
+        ui.end_row();
+
+        ui.label("Add tags");
+        ui.add(egui::TextEdit::singleline(&mut rule.tags).hint_text("comma separated"));
+        ui.end_row();
+    });
+}
+
+/// A checkbox to enable the value and its editor
+fn optional<T>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<T>,
+    default: T,
+    add: impl FnOnce(&mut egui::Ui, &mut T),
+) {
+    let mut enabled = value.is_some();
+    if ui.checkbox(&mut enabled, label).changed() {
+        *value = enabled.then_some(default);
+    }
+    if let Some(v) = value {
+        add(ui, v);
+    }
+}
@@ -2,7 +2,7 @@ use chrono::{self, Datelike};
 use egui_extras::Column;
 use itertools::Itertools;
 
-use crate::common::to_name;
+use crate::common::{self, to_name};
 use crate::model::FinItem;
 use crate::TemplateApp;
 
@@ -12,7 +12,7 @@ pub(crate) fn show(
     ui: &mut egui::Ui,
     app: &mut TemplateApp,
     items_in_month: &[FinItem],
-    possible_years: &Vec<i32>,
+    possible_years: &[i32],
     paid_dict: HashMap<String, (f32, f32)>,
 ) {
     ui.heading("Details");
@@ -155,23 +155,7 @@ pub(crate) fn show(
                     });
 
                 // order items by category and sum them up
-                let cat_dict = items_in_month
-                    .iter()
-                    .map(|i| {
-                        let x = i
-                            .category
-                            .as_ref()
-                            .unwrap_or(&"None".to_string())
-                            .to_string();
-                        (x, i.price)
-                    })
-                    .into_group_map()
-                    .iter()
-                    .map(|(k, v)| {
-                        let result: f32 = v.iter().sum();
-                        (k.to_string(), result)
-                    })
-                    .collect::<HashMap<_, _>>();
+                let cat_dict = common::spent_by_category(items_in_month);
 
                 // view table
                 category_table.body(|mut body| {
@@ -0,0 +1,21 @@
+use crate::TemplateApp;
+
+/// Tell why the app started empty instead of with the saved data
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    let Some(error) = &app.state_error else {
+        return;
+    };
+
+    let mut close = false;
+    egui::Window::new("Saved data not loaded")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+            close = ui.button("OK").clicked();
+        });
+    if close {
+        app.state_error = None;
+    }
+}
@@ -1,57 +1,156 @@
 use log::warn;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader, LineWriter, Write},
-};
 
-use crate::{model::FinItem, TemplateApp};
+use crate::{
+    document::DocumentAction,
+    files::save_file,
+    files::FileKind,
+    formats::{self, ImportFormat},
+    undo::{REDO_SHORTCUT, UNDO_SHORTCUT},
+    views, TemplateApp,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{files, sync::SyncFolder};
 
 pub(crate) fn show(ui: &mut egui::Ui, _frame: &mut eframe::Frame, app: &mut TemplateApp) {
     egui::menu::bar(ui, |ui| {
         // menu bar starting from left
         ui.menu_button("File", |ui| {
-            // Import button
-            #[cfg(not(target_arch = "wasm32"))] // no File->Export on web pages!
-            if ui.button("Import Data").clicked() {
-                let file_option = rfd::FileDialog::new()
-                    .add_filter("csv", &["csv"])
-                    .set_directory("/")
-                    .pick_file();
-
-                if let Some(path) = file_option {
-                    if let Ok(file) = File::open(path.as_path()) {
-                        for (i, line) in BufReader::new(file).lines().enumerate() {
-                            if let Some(item) = parse_line(line) {
-                                app.items.push(item);
-                            } else {
-                                warn!("Failed to parse line {}", i)
-                            }
+            // ledger documents
+            if ui.button("New").clicked() {
+                app.request(DocumentAction::New);
+                ui.close_menu();
+            }
+
+            if ui.button("Open…").clicked() {
+                app.request(DocumentAction::Open);
+                ui.close_menu();
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.add_enabled_ui(!app.recent_files.is_empty(), |ui| {
+                ui.menu_button("Open Recent", |ui| {
+                    for path in app.recent_files.clone() {
+                        if ui.button(path.display().to_string()).clicked() {
+                            app.request(DocumentAction::OpenRecent(path));
+                            ui.close_menu();
                         }
                     }
-                }
+                });
+            });
+
+            if ui.button("Save").clicked() {
+                app.save();
+                ui.close_menu();
             }
 
-            // Export button
-            #[cfg(not(target_arch = "wasm32"))] // no File->Export on web pages!
-            if ui.button("Export Data").clicked() {
-                let some_path = rfd::FileDialog::new()
-                    .add_filter("csv", &["csv"])
-                    .set_directory("/")
-                    .save_file();
-
-                if let Some(path) = some_path {
-                    if let Ok(file) = File::create(path.as_path()) {
-                        let mut fw = LineWriter::new(file);
-                        for i in app.items.iter() {
-                            let str = format!("{i}\n");
-                            match fw.write(str.as_bytes()) {
-                                Ok(_) => {}
-                                Err(e) => warn!("Failed to write line {}", e),
-                            }
-                        }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Save As…").clicked() {
+                app.save_as();
+                ui.close_menu();
+            }
+
+            #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+            if ui
+                .add_enabled(app.cipher.is_none(), egui::Button::new("Save As SQLite…"))
+                .on_disabled_hover_text(
+                    "SQLite documents are not encrypted, remove the passphrase first",
+                )
+                .clicked()
+            {
+                app.save_as_store();
+                ui.close_menu();
+            }
+
+            if ui.button("Passphrase…").clicked() {
+                app.show_passphrase = true;
+                ui.close_menu();
+            }
+
+            if ui.button("Restore Backup…").clicked() {
+                app.show_backups = true;
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            // Import menu
+            ui.menu_button("Import", |ui| {
+                for format in ImportFormat::ALL {
+                    if ui.button(format.name()).clicked() {
+                        app.inbox.pick(FileKind::Import(format));
+                        ui.close_menu();
                     }
                 }
-            }
+
+                ui.separator();
+                ui.checkbox(&mut app.credits_as_income, "Import credits as income");
+            });
+
+            // Export menu
+            ui.menu_button("Export", |ui| {
+                if ui.button("CSV").clicked() {
+                    save_file("csv", "csv", formats::csv::write(&app.items));
+                    ui.close_menu();
+                }
+
+                if ui.button("JSON").clicked() {
+                    save_file("json", "json", formats::json::write(&app.items));
+                    ui.close_menu();
+                }
+
+                if ui.button("QIF").clicked() {
+                    save_file("qif", "qif", formats::qif::write(&app.items));
+                    ui.close_menu();
+                }
+
+                if ui.button("hledger/ledger").clicked() {
+                    let journal = formats::journal::write_ledger(&app.items);
+                    save_file("journal", "journal", journal);
+                    ui.close_menu();
+                }
+
+                if ui.button("Beancount").clicked() {
+                    let beancount = formats::journal::write_beancount(&app.items);
+                    save_file("beancount", "beancount", beancount);
+                    ui.close_menu();
+                }
+
+                let people = formats::people(&app.items);
+                if ui.button("Splitwise").clicked() {
+                    let csv = formats::splitwise::write(&app.items, &people);
+                    save_file("splitwise", "csv", csv);
+                    ui.close_menu();
+                }
+
+                if ui.button("Tricount").clicked() {
+                    let csv = formats::tricount::write(&app.items, &people);
+                    save_file("tricount", "csv", csv);
+                    ui.close_menu();
+                }
+
+                if ui.button("iCalendar").clicked() {
+                    save_file("ics", "ics", formats::ical::write(&app.items));
+                    ui.close_menu();
+                }
+
+                if ui.button("XLSX").clicked() {
+                    let sheets = formats::spreadsheet::workbook(&app.items);
+                    match formats::xlsx::write(&sheets) {
+                        Ok(bytes) => save_file("xlsx", "xlsx", bytes),
+                        Err(e) => warn!("Failed to export XLSX: {}", e),
+                    }
+                    ui.close_menu();
+                }
+
+                if ui.button("ODS").clicked() {
+                    let sheets = formats::spreadsheet::workbook(&app.items);
+                    match formats::ods::write(&sheets) {
+                        Ok(bytes) => save_file("ods", "ods", bytes),
+                        Err(e) => warn!("Failed to export ODS: {}", e),
+                    }
+                    ui.close_menu();
+                }
+            });
 
             // Quit button
             #[cfg(not(target_arch = "wasm32"))] // no File->Quit on web pages!
@@ -60,6 +159,119 @@ pub(crate) fn show(ui: &mut egui::Ui, _frame: &mut eframe::Frame, app: &mut Temp
             }
         });
 
+        ui.menu_button("Edit", |ui| {
+            let undo =
+                egui::Button::new("Undo").shortcut_text(ui.ctx().format_shortcut(&UNDO_SHORTCUT));
+            if ui.add_enabled(app.can_undo(), undo).clicked() {
+                app.undo();
+                ui.close_menu();
+            }
+            let redo =
+                egui::Button::new("Redo").shortcut_text(ui.ctx().format_shortcut(&REDO_SHORTCUT));
+            if ui.add_enabled(app.can_redo(), redo).clicked() {
+                app.redo();
+                ui.close_menu();
+            }
+
+            ui.separator();
+            if ui.button(format!("Trash ({})…", app.trash.len())).clicked() {
+                app.show_trash = true;
+                ui.close_menu();
+            }
+        });
+
+        // switch between the ledgers, each saved to its own document
+        ui.menu_button(format!("Ledger: {}", app.workspace), |ui| {
+            for workspace in app.workspace_names() {
+                let shown = workspace == app.workspace;
+                if ui.selectable_label(shown, &workspace).clicked() {
+                    app.switch_workspace(&workspace);
+                    ui.close_menu();
+                }
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut app.new_workspace).hint_text("Name"));
+                if ui.button("Add").clicked() && app.add_workspace(&app.new_workspace.clone()) {
+                    app.new_workspace.clear();
+                    ui.close_menu();
+                }
+            });
+
+            let several = !app.workspaces.is_empty();
+            if ui
+                .add_enabled(several, egui::Button::new("Close Ledger"))
+                .clicked()
+            {
+                app.request(DocumentAction::CloseLedger);
+                ui.close_menu();
+            }
+        });
+
+        // reports of the selected month
+        ui.menu_button("Report", |ui| {
+            if ui.button("Export HTML").clicked() {
+                let report = app.month_report();
+                save_file("html", "html", report.html());
+                ui.close_menu();
+            }
+
+            if ui.button("Export PDF").clicked() {
+                let report = app.month_report();
+                save_file("pdf", "pdf", report.pdf());
+                ui.close_menu();
+            }
+
+            ui.separator();
+            views::bottom_panel_view::chart_export_buttons(ui, app);
+        });
+
+        // share the shown ledger with other devices
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.menu_button("Sync", |ui| {
+            match &app.sync {
+                Some(sync) => ui.label(format!("Synced through {}", sync.folder.display())),
+                None => ui.label("Not synced"),
+            };
+            ui.label(format!("This device is {}", app.device));
+            ui.separator();
+
+            if ui.button("Sync Folder…").clicked() {
+                if let Some(folder) = files::pick_folder() {
+                    app.sync = Some(SyncFolder::new(folder));
+                    app.sync_now(true);
+                }
+                ui.close_menu();
+            }
+
+            ui.add_enabled_ui(app.sync.is_some(), |ui| {
+                if ui.button("Sync Now").clicked() {
+                    app.sync_now(true);
+                    ui.close_menu();
+                }
+
+                let conflicts = app.sync.as_ref().map_or(0, |s| s.conflicts.len());
+                if ui.button(format!("Conflicts ({conflicts})…")).clicked() {
+                    app.show_conflicts = true;
+                    ui.close_menu();
+                }
+
+                if ui.button("Stop Syncing").clicked() {
+                    app.sync = None;
+                    ui.close_menu();
+                }
+            });
+        });
+
+        if ui.button("Rules").clicked() {
+            app.show_rules = true;
+        }
+
+        if ui.button("Activity").clicked() {
+            app.show_activity = true;
+        }
+
         // theme button on right
         ui.with_layout(egui::Layout::right_to_left(egui::Align::RIGHT), |ui| {
             egui::widgets::global_dark_light_mode_switch(ui);
@@ -68,19 +280,3 @@ pub(crate) fn show(ui: &mut egui::Ui, _frame: &mut eframe::Frame, app: &mut Temp
         });
     });
 }
-
-fn parse_line(line: Result<String, std::io::Error>) -> Option<FinItem> {
-    match line {
-        Ok(s) => match s.as_str().parse::<FinItem>() {
-            Ok(item) => Some(item),
-            Err(_) => {
-                warn!("Failed to parse line. Error:");
-                None
-            }
-        },
-        Err(err) => {
-            warn!("Failed to read line {}", err);
-            None
-        }
-    }
-}
@@ -0,0 +1,98 @@
+use crate::TemplateApp;
+
+/// The deleted items of the ledger, and the confirmation before deleting
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    trash(ctx, app);
+    confirm_delete(ctx, app);
+}
+
+fn trash(ctx: &egui::Context, app: &mut TemplateApp) {
+    let mut open = app.show_trash;
+    let mut to_restore = None;
+    let mut to_purge = None;
+    egui::Window::new("Trash")
+        .open(&mut open)
+        .vscroll(true)
+        .show(ctx, |ui| {
+            ui.checkbox(&mut app.confirm_delete, "Ask before deleting");
+            ui.horizontal(|ui| {
+                ui.label("Purge deleted items after");
+                let days = ui.add(egui::DragValue::new(&mut app.trash_days).suffix(" days"));
+                // not while dragging, e.g. through 1 on the way to 0
+                if days.drag_released() || days.lost_focus() {
+                    app.purge_old_trash();
+                }
+                ui.weak("0 keeps them");
+            });
+            ui.separator();
+
+            if app.trash.is_empty() {
+                ui.label("The trash is empty.");
+            } else if ui.button("Empty Trash").clicked() {
+                app.trash.clear();
+            }
+
+            egui::Grid::new("trash").striped(true).show(ui, |ui| {
+                for (i, trashed) in app.trash.iter().enumerate().rev() {
+                    let item = &trashed.item;
+                    ui.label(format!(
+                        "{} {} {:.2} ({})",
+                        item.date, item.item, item.price, item.owner
+                    ));
+                    ui.weak(format!(
+                        "deleted {}",
+                        trashed.deleted.format("%Y-%m-%d %H:%M")
+                    ));
+                    if ui.button("Restore").clicked() {
+                        to_restore = Some(i);
+                    }
+                    if ui.button("Purge").clicked() {
+                        to_purge = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    if let Some(i) = to_restore {
+        app.restore_trashed(i);
+    }
+    if let Some(i) = to_purge {
+        app.trash.remove(i);
+    }
+    app.show_trash = open;
+}
+
+fn confirm_delete(ctx: &egui::Context, app: &mut TemplateApp) {
+    let Some(id) = app.pending_delete else {
+        return;
+    };
+    let Some(item) = app.items.iter().find(|i| i.id == id) else {
+        app.pending_delete = None;
+        return;
+    };
+
+    let mut delete = false;
+    let mut cancel = false;
+    egui::Window::new("Delete item")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Move {} {} {:.2} to the trash?",
+                item.date, item.item, item.price
+            ));
+            ui.horizontal(|ui| {
+                delete = ui.button("Delete").clicked();
+                cancel = ui.button("Cancel").clicked();
+            });
+        });
+
+    if delete {
+        app.delete_item(id);
+    }
+    if delete || cancel {
+        app.pending_delete = None;
+    }
+}
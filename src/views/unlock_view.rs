@@ -0,0 +1,32 @@
+use crate::TemplateApp;
+
+/// Ask for the passphrase of the encrypted state before showing anything
+pub(crate) fn show(ctx: &egui::Context, frame: &mut eframe::Frame, app: &mut TemplateApp) {
+    egui::CentralPanel::default().show(ctx, |_ui| {});
+
+    let mut unlock = false;
+    egui::Window::new("Unlock")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label("The saved data is encrypted, enter the passphrase to unlock it.");
+            let response = ui
+                .add(egui::TextEdit::singleline(&mut app.passphrase_input.current).password(true));
+            if let Some(error) = &app.passphrase_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            unlock = ui.button("Unlock").clicked()
+                || (response.lost_focus() && ui.input().key_pressed(egui::Key::Enter));
+            if !response.has_focus() {
+                response.request_focus();
+            }
+        });
+
+    if unlock {
+        let passphrase = std::mem::take(&mut app.passphrase_input.current);
+        if let Some(storage) = frame.storage() {
+            app.unlock(storage, &passphrase);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::TemplateApp;
+
+/// Ask to save before a document action discards unsaved changes
+pub(crate) fn show(ctx: &egui::Context, app: &mut TemplateApp) {
+    let Some(action) = app.pending_action.clone() else {
+        return;
+    };
+
+    egui::Window::new("Unsaved changes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(format!("Save the changes to {}?", app.document_name()));
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    app.pending_action = None;
+                    app.save();
+                    // unless saving was cancelled
+                    if !app.is_dirty() {
+                        app.run(action.clone());
+                    }
+                }
+                if ui.button("Discard").clicked() {
+                    app.pending_action = None;
+                    app.run(action.clone());
+                }
+                if ui.button("Cancel").clicked() {
+                    app.pending_action = None;
+                }
+            });
+        });
+}
@@ -0,0 +1,264 @@
+use log::{info, warn};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::app::TemplateApp;
+use crate::ledger::Ledger;
+use crate::model::FinItem;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sync::SyncFolder;
+
+/// Name of the ledger shown before any other is added
+pub(crate) const DEFAULT_NAME: &str = "Household";
+
+/// A named ledger kept in the background while another one is shown.
+/// It keeps its unsaved changes until switched to again.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub(crate) struct Workspace {
+    pub(crate) name: String,
+    pub(crate) ledger: Ledger,
+    pub(crate) credits_as_income: bool,
+    pub(crate) document: Option<PathBuf>,
+    #[cfg(not(target_arch = "wasm32"))]
+    sync: Option<SyncFolder>,
+    /// The ledger as last opened or saved
+    #[serde(skip)]
+    saved: Ledger,
+}
+
+impl TemplateApp {
+    /// Names of all ledgers, the shown one first
+    pub(crate) fn workspace_names(&self) -> Vec<String> {
+        std::iter::once(self.workspace.clone())
+            .chain(self.workspaces.iter().map(|w| w.name.clone()))
+            .collect()
+    }
+
+    /// Show another ledger, keeping the shown one with its unsaved changes
+    pub(crate) fn switch_workspace(&mut self, name: &str) {
+        let Some(index) = self.workspaces.iter().position(|w| w.name == name) else {
+            return;
+        };
+        let current = self.take_workspace();
+        let next = std::mem::replace(&mut self.workspaces[index], current);
+        self.show_workspace(next);
+        info!("Switched to ledger {}", self.workspace);
+    }
+
+    /// Add an empty ledger with the categories of the shown one and show it.
+    /// Returns false if the name is empty or taken.
+    pub(crate) fn add_workspace(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() {
+            return false;
+        }
+        if self.workspace_names().iter().any(|n| n == name) {
+            warn!("There is a ledger named {} already", name);
+            return false;
+        }
+
+        let current = self.take_workspace();
+        self.workspaces.push(current);
+        let ledger = Ledger {
+            categories: self.categories.clone(),
+            ..Ledger::default()
+        };
+        self.show_workspace(Workspace {
+            name: name.to_owned(),
+            saved: ledger.clone(),
+            ledger,
+            ..Workspace::default()
+        });
+        true
+    }
+
+    /// Drop the shown ledger with its unsaved changes and show the next one
+    pub(crate) fn close_workspace(&mut self) {
+        if self.workspaces.is_empty() {
+            return;
+        }
+        let closed = self.take_workspace();
+        let next = self.workspaces.remove(0);
+        self.show_workspace(next);
+        info!("Closed ledger {}", closed.name);
+    }
+
+    /// Move the selected items to another ledger
+    pub(crate) fn move_selected_to(&mut self, name: &str) {
+        let Some(target) = self.workspaces.iter_mut().find(|w| w.name == name) else {
+            return;
+        };
+        let (moved, kept): (Vec<FinItem>, Vec<FinItem>) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition(|i| i.selected);
+        self.items = kept;
+        self.save_state = true;
+        info!("Moved {} items to {}", moved.len(), name);
+        target
+            .ledger
+            .items
+            .extend(moved.into_iter().map(|mut item| {
+                item.selected = false;
+                item
+            }));
+        // undoing would leave the items in both ledgers
+        self.clear_undo();
+    }
+
+    /// Copy the selected items to another ledger. The copies get new ids, they
+    /// are separate items from then on.
+    pub(crate) fn copy_selected_to(&mut self, name: &str) {
+        let Some(target) = self.workspaces.iter_mut().find(|w| w.name == name) else {
+            return;
+        };
+        let copies: Vec<FinItem> = self
+            .items
+            .iter()
+            .filter(|i| i.selected)
+            .map(|item| FinItem {
+                id: Uuid::new_v4(),
+                selected: false,
+                ..item.clone()
+            })
+            .collect();
+        info!("Copied {} items to {}", copies.len(), name);
+        self.save_state = true;
+        target.ledger.items.extend(copies);
+    }
+
+    /// Put the shown ledger aside with its unsaved changes
+    fn take_workspace(&mut self) -> Workspace {
+        #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+        {
+            self.store = None;
+        }
+        self.save_state = true;
+        Workspace {
+            name: std::mem::take(&mut self.workspace),
+            ledger: self.ledger(),
+            credits_as_income: self.credits_as_income,
+            document: self.document.take(),
+            #[cfg(not(target_arch = "wasm32"))]
+            sync: self.sync.take(),
+            saved: std::mem::take(&mut self.saved),
+        }
+    }
+
+    fn show_workspace(&mut self, workspace: Workspace) {
+        self.workspace = workspace.name;
+        self.credits_as_income = workspace.credits_as_income;
+        self.document = workspace.document;
+        self.saved = workspace.saved;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.sync = workspace.sync;
+        }
+        self.owners_compare = ("None".to_owned(), "None".to_owned());
+        // read what the document has saved, but keep the changes
+        self.reopen_document();
+        self.set_ledger(workspace.ledger);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn item(name: &str, price: f32) -> FinItem {
+        FinItem::new(
+            NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(),
+            name.to_owned(),
+            price,
+        )
+    }
+
+    /// "Household" with Rent and Power shown, "Holiday" in the background
+    fn app() -> TemplateApp {
+        let mut app = TemplateApp::default();
+        app.workspace = DEFAULT_NAME.to_owned();
+        app.categories = vec!["bills".to_owned()];
+        assert!(app.add_workspace("Holiday"));
+        app.switch_workspace(DEFAULT_NAME);
+        app.items = vec![item("Rent", 800.0), item("Power", 60.0)];
+        app
+    }
+
+    fn holiday(app: &TemplateApp) -> &Workspace {
+        app.workspaces.iter().find(|w| w.name == "Holiday").unwrap()
+    }
+
+    #[test]
+    fn keeps_unsaved_changes_on_switch() {
+        let mut app = app();
+        app.switch_workspace("Holiday");
+        assert_eq!(app.workspace, "Holiday");
+        assert!(app.items.is_empty());
+        assert_eq!(app.categories, ["bills"]);
+
+        app.switch_workspace(DEFAULT_NAME);
+        assert_eq!(app.items.len(), 2);
+        assert!(app.is_dirty());
+        assert_eq!(app.workspace_names(), [DEFAULT_NAME, "Holiday"]);
+    }
+
+    #[test]
+    fn rejects_empty_and_taken_names() {
+        let mut app = app();
+        assert!(!app.add_workspace(" "));
+        assert!(!app.add_workspace("Holiday"));
+        assert!(!app.add_workspace(" Household "));
+        assert_eq!(app.workspaces.len(), 1);
+        assert_eq!(app.workspace, DEFAULT_NAME);
+    }
+
+    #[test]
+    fn moves_the_selected_items() {
+        let mut app = app();
+        app.items[0].selected = true;
+        let rent = app.items[0].id;
+        app.move_selected_to("Holiday");
+
+        assert_eq!(app.items.len(), 1);
+        assert_eq!(app.items[0].item, "Power");
+        let moved = &holiday(&app).ledger.items;
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].id, rent);
+        assert!(!moved[0].selected);
+        assert!(!app.can_undo());
+
+        // to a ledger that is not there, nothing happens
+        app.items[0].selected = true;
+        app.move_selected_to("Work");
+        assert_eq!(app.items.len(), 1);
+    }
+
+    #[test]
+    fn copies_the_selected_items_with_new_ids() {
+        let mut app = app();
+        app.items[1].selected = true;
+        app.copy_selected_to("Holiday");
+
+        assert_eq!(app.items.len(), 2);
+        let copies = &holiday(&app).ledger.items;
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].item, "Power");
+        assert_ne!(copies[0].id, app.items[1].id);
+        assert!(!copies[0].selected);
+    }
+
+    #[test]
+    fn closes_the_shown_ledger() {
+        let mut app = app();
+        app.close_workspace();
+        assert_eq!(app.workspace, "Holiday");
+        assert!(app.workspaces.is_empty());
+        assert!(app.items.is_empty());
+
+        // the last ledger stays
+        app.close_workspace();
+        assert_eq!(app.workspace, "Holiday");
+    }
+}